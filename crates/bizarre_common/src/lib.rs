@@ -1,2 +1,3 @@
 pub mod handle;
 pub mod resources;
+pub mod system_error;
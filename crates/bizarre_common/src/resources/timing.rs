@@ -5,3 +5,12 @@ pub struct DeltaTime(pub Duration);
 
 #[derive(Default, Clone)]
 pub struct RunningTime(pub Duration);
+
+/// Step of the fixed update schedule
+#[derive(Default, Clone)]
+pub struct FixedDeltaTime(pub Duration);
+
+/// How far the frame is between the last and the next fixed update, in
+/// `[0, 1)`. Used to interpolate fixed update state for rendering.
+#[derive(Default, Clone)]
+pub struct FixedTimestepAlpha(pub f32);
@@ -0,0 +1,99 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use specs::{shrev::EventChannel, Read, System, SystemData, World, Write};
+
+/// A system that can fail. Add it with `AppBuilder::add_try_system`, its
+/// errors are sent as [`SystemError`] events and handled by the error policy
+/// of the app.
+pub trait TrySystem<'a> {
+    type SystemData: SystemData<'a>;
+
+    fn try_run(&mut self, data: Self::SystemData) -> anyhow::Result<()>;
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+    }
+}
+
+#[derive(Debug)]
+pub struct SystemError {
+    pub system: Arc<str>,
+    pub error: anyhow::Error,
+    /// Runs in a row that failed, including this one
+    pub consecutive_failures: u32,
+}
+
+/// Systems skipped by [`FallibleSystem`], filled by the error policy
+#[derive(Debug, Default)]
+pub struct DisabledSystems(BTreeSet<String>);
+
+impl DisabledSystems {
+    pub fn is_disabled(&self, system: &str) -> bool {
+        self.0.contains(system)
+    }
+
+    pub fn disable(&mut self, system: &str) {
+        self.0.insert(system.into());
+    }
+
+    /// Returns whether the system was disabled
+    pub fn enable(&mut self, system: &str) -> bool {
+        self.0.remove(system)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+/// Runs a [`TrySystem`] as a regular system, sending its errors to the
+/// `EventChannel<SystemError>`
+pub struct FallibleSystem<S> {
+    pub inner: S,
+    name: Arc<str>,
+    consecutive_failures: u32,
+}
+
+impl<S> FallibleSystem<S> {
+    pub fn new(inner: S, name: &str) -> Self {
+        Self {
+            inner,
+            name: name.into(),
+            consecutive_failures: 0,
+        }
+    }
+}
+
+impl<'a, S> System<'a> for FallibleSystem<S>
+where
+    S: TrySystem<'a>,
+{
+    type SystemData = (
+        Read<'a, DisabledSystems>,
+        Write<'a, EventChannel<SystemError>>,
+        S::SystemData,
+    );
+
+    fn run(&mut self, (disabled, mut errors, data): Self::SystemData) {
+        if disabled.is_disabled(&self.name) {
+            return;
+        }
+
+        match self.inner.try_run(data) {
+            Ok(()) => self.consecutive_failures = 0,
+            Err(error) => {
+                self.consecutive_failures += 1;
+                errors.single_write(SystemError {
+                    system: self.name.clone(),
+                    error,
+                    consecutive_failures: self.consecutive_failures,
+                });
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        <(Read<DisabledSystems>, Write<EventChannel<SystemError>>)>::setup(world);
+        self.inner.setup(world);
+    }
+}
@@ -1,17 +1,40 @@
 use std::{
-    sync::mpsc::{channel, TryRecvError},
+    path::PathBuf,
+    sync::{
+        mpsc::{channel, Receiver, Sender, TryRecvError},
+        Mutex,
+    },
     time::{Duration, Instant},
 };
 
-use bizarre_logger::{core_critical, core_info, global_loggers::logging_thread_join};
-use specs::{shrev::EventChannel, ReaderId, WorldExt};
+use bizarre_logger::{
+    core_critical, core_debug, core_error, core_info, global_loggers::logging_thread_join,
+    logger_impl::CORE_LOGGER_NAME, span::log_span_stats,
+};
+use specs::{shrev::EventChannel, DispatcherBuilder, ReaderId, World, WorldExt};
 
 use crate::{
-    app_builder::AppBuilder, app_events::AppCloseRequestedEvent, debug_stats::DebugStats,
-    schedule::Schedule,
+    app_builder::{engine_console, engine_cvars, AppBuilder},
+    app_events::AppCloseRequestedEvent,
+    app_state::StateTransitionDriver,
+    close_control::CloseControl,
+    console::{self, CVarRegistry, Console},
+    core_events::WindowFocusChanged,
+    debug_stats::DebugStats,
+    error_policy::{ErrorAction, ErrorPolicies, FailureLog},
+    fixed_timestep::FixedTimestep,
+    frame_pacing::{FramePacer, FramePacing},
+    input::InputPlayer,
+    layer::{validate_dependencies, AttachedLayer, Layer, LayerError},
+    profiler::Profiler,
+    schedule::{Schedule, ScheduleBuilder, ScheduleInfo},
+    time::Time,
 };
 
-use bizarre_common::resources::{DeltaTime, RunningTime};
+use bizarre_common::{
+    resources::{DeltaTime, FixedDeltaTime, FixedTimestepAlpha, RunningTime},
+    system_error::{DisabledSystems, SystemError},
+};
 
 pub struct App {
     pub(crate) name: Box<str>,
@@ -19,6 +42,51 @@ pub struct App {
     pub(crate) schedule: Schedule,
     pub(crate) running: bool,
     pub(crate) app_close_reader: Option<ReaderId<AppCloseRequestedEvent>>,
+    pub(crate) fixed_timestep: FixedTimestep,
+    pub(crate) frame_pacing: FramePacing,
+    pub(crate) frame_pacer: FramePacer,
+    pub(crate) last_frame_start: Option<Instant>,
+    pub(crate) focus_reader: Option<ReaderId<WindowFocusChanged>>,
+    pub(crate) close_receiver: Option<Receiver<AppCloseRequestedEvent>>,
+    pub(crate) initialized: bool,
+    pub(crate) layers: Vec<AttachedLayer>,
+    pub(crate) chrome_trace_path: Option<PathBuf>,
+    pub(crate) state_drivers: Vec<Box<dyn StateTransitionDriver>>,
+    pub(crate) error_policies: ErrorPolicies,
+    pub(crate) failure_log: FailureLog,
+    pub(crate) system_error_reader: Option<ReaderId<SystemError>>,
+}
+
+/// The termination handler can be set only once per process, so it forwards
+/// to whichever app is currently running. It handles ctrl-c, SIGTERM and
+/// SIGHUP.
+static TERMINATION_SENDER: Mutex<Option<Sender<AppCloseRequestedEvent>>> = Mutex::new(None);
+
+fn install_termination_handler() -> Result<Receiver<AppCloseRequestedEvent>, ctrlc::Error> {
+    static HANDLER_INSTALLED: Mutex<bool> = Mutex::new(false);
+
+    let mut installed = HANDLER_INSTALLED
+        .lock()
+        .expect("Failed to lock the termination handler state");
+
+    if !*installed {
+        ctrlc::set_handler(|| {
+            let sender = TERMINATION_SENDER
+                .lock()
+                .expect("Failed to lock the termination sender");
+            if let Some(sender) = sender.as_ref() {
+                let _ = sender.send(AppCloseRequestedEvent {});
+            }
+        })?;
+        *installed = true;
+    }
+
+    let (tx, rx) = channel();
+    *TERMINATION_SENDER
+        .lock()
+        .expect("Failed to lock the termination sender") = Some(tx);
+
+    Ok(rx)
 }
 
 impl App {
@@ -29,25 +97,72 @@ impl App {
             schedule: Schedule::default(),
             running: false,
             app_close_reader: None,
+            fixed_timestep: FixedTimestep::default(),
+            frame_pacing: FramePacing::default(),
+            frame_pacer: FramePacer::default(),
+            last_frame_start: None,
+            focus_reader: None,
+            close_receiver: None,
+            initialized: false,
+            layers: Vec::new(),
+            chrome_trace_path: None,
+            state_drivers: Vec::new(),
+            error_policies: ErrorPolicies::default(),
+            failure_log: FailureLog::default(),
+            system_error_reader: None,
         }
     }
 
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Systems of the app schedule in execution order. Layers pushed at
+    /// runtime are not included.
+    pub fn schedule_info(&self) -> &ScheduleInfo {
+        &self.schedule.info
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Runs the app until a close is requested, then destroys it and stops
+    /// the logger thread
     pub fn run(&mut self) {
         core_info!("Running the \"{}\" application", self.name);
 
-        let (tx, rx) = channel::<AppCloseRequestedEvent>();
-        {
-            let tx = tx.clone();
-            let result = ctrlc::set_handler(move || {
-                let _ = tx.send(AppCloseRequestedEvent {});
-            });
-            if let Err(e) = result {
+        match install_termination_handler() {
+            Ok(receiver) => self.close_receiver = Some(receiver),
+            Err(e) => {
                 core_critical!("Failed to set a termination handler: {}", e);
                 self.destroy();
                 return;
             }
         }
 
+        self.init();
+
+        while self.running {
+            self.update();
+        }
+
+        self.destroy();
+
+        logging_thread_join();
+    }
+
+    /// Inserts the engine resources and runs the setup schedule. Called by the
+    /// first [`App::update`] or [`App::step`] if not called before.
+    pub fn init(&mut self) {
+        if self.initialized {
+            return;
+        }
+
         {
             let mut event_channel = EventChannel::<AppCloseRequestedEvent>::new();
             self.app_close_reader = Some(event_channel.register_reader());
@@ -55,82 +170,413 @@ impl App {
             self.world.insert(event_channel);
         }
 
+        {
+            let mut event_channel = EventChannel::<WindowFocusChanged>::new();
+            self.focus_reader = Some(event_channel.register_reader());
+
+            self.world.insert(event_channel);
+        }
+
+        {
+            let mut event_channel = EventChannel::<SystemError>::new();
+            self.system_error_reader = Some(event_channel.register_reader());
+
+            self.world.insert(event_channel);
+        }
+
+        self.world.insert(self.frame_pacing);
+        self.world.entry::<Time>().or_insert_with(Time::default);
+        self.world.entry::<Console>().or_insert_with(engine_console);
+        self.world
+            .entry::<CVarRegistry>()
+            .or_insert_with(engine_cvars);
         self.world.insert(DeltaTime(Duration::from_secs(0)));
         self.world.insert(RunningTime(Duration::from_secs(0)));
         self.world.insert(DebugStats::default());
+        self.world
+            .insert(FixedDeltaTime(self.fixed_timestep.step()));
+        self.world.insert(FixedTimestepAlpha(0.0));
+        self.world.insert(CloseControl::default());
+        self.world.insert(DisabledSystems::default());
+        self.world
+            .entry::<Profiler>()
+            .or_insert_with(Profiler::default);
 
         self.schedule.setup_dispatcher.setup(&mut self.world);
         self.schedule.setup_dispatcher.dispatch(&self.world);
         self.world.maintain();
 
+        self.schedule.fixed_dispatcher.setup(&mut self.world);
         self.schedule.frame_dispatcher.setup(&mut self.world);
+        self.schedule.shutdown_dispatcher.setup(&mut self.world);
 
+        for driver in &mut self.state_drivers {
+            driver.init(&mut self.world);
+        }
+
+        self.initialized = true;
         self.running = true;
 
-        while self.running {
-            let frame_start = Instant::now();
+        self.handle_system_errors();
+    }
 
-            self.schedule.frame_dispatcher.dispatch(&self.world);
+    /// Runs a single frame, waiting for the rest of the frame budget of the
+    /// [`FramePacing`] resource. Time is advanced before the systems run, so
+    /// `DeltaTime` is the time since the previous frame started, zero on the
+    /// first one, with the [`Time`] scale and pause applied. While an
+    /// [`InputPlayer`] is playing, the recorded delta is used instead.
+    pub fn update(&mut self) {
+        self.init();
 
-            let frame_duration = Instant::now() - frame_start;
-            let sleep_duration = Duration::from_millis(16).saturating_sub(frame_duration);
-            let delta_time = DeltaTime(frame_duration + sleep_duration);
-            {
-                let mut running_time = self.world.write_resource::<RunningTime>();
-                let mut delta_time_res = self.world.write_resource::<DeltaTime>();
+        if !self.running {
+            return;
+        }
+
+        let frame_start = Instant::now();
+        let delta_time = match self.last_frame_start.replace(frame_start) {
+            Some(last_frame_start) => frame_start - last_frame_start,
+            None => Duration::ZERO,
+        };
+        let delta_time = self.playback_delta().unwrap_or(delta_time);
+
+        self.advance_time(delta_time);
+
+        let frame_duration = self.run_frame();
 
-                *running_time = RunningTime(running_time.0 + delta_time.0);
-                *delta_time_res = delta_time.clone();
-                let mut debug_stats = self.world.write_resource::<DebugStats>();
+        if !self.running {
+            return;
+        }
+
+        let pacing = *self.world.read_resource::<FramePacing>();
+        let sleep_duration = self.frame_pacer.wait(&pacing, frame_start);
+
+        self.record_frame_stats(frame_duration, sleep_duration, frame_start.elapsed());
+    }
+
+    /// Runs a single frame as if `delta` has passed since the previous one,
+    /// without any frame pacing. Like [`App::update`], time is advanced
+    /// before the systems run. Meant for deterministic tests, the [`Time`]
+    /// scale and pause still apply.
+    pub fn step(&mut self, delta: Duration) {
+        self.init();
+
+        if !self.running {
+            return;
+        }
+
+        self.advance_time(delta);
+
+        let frame_duration = self.run_frame();
+
+        self.record_frame_stats(frame_duration, Duration::ZERO, frame_duration);
+    }
 
-                debug_stats.last_frame_work_time_ms = frame_duration.as_secs_f64() * 1000.0;
-                debug_stats.last_frame_idle_time_ms = sleep_duration.as_secs_f64() * 1000.0;
-                debug_stats.last_frame_total_time_ms = delta_time.0.as_secs_f64() * 1000.0;
+    fn record_frame_stats(&mut self, work: Duration, idle: Duration, total: Duration) {
+        let profiler = self.world.read_resource::<Profiler>();
+        let mut debug_stats = self.world.write_resource::<DebugStats>();
+
+        debug_stats.record_frame(work, idle, total);
+        debug_stats.system_times = profiler.system_stats();
+    }
+
+    /// Runs up to `frames` frames with [`App::update`], stopping early if a
+    /// close is requested. Returns the number of frames run.
+    pub fn run_frames(&mut self, frames: usize) -> usize {
+        self.init();
+
+        let mut count = 0;
+        while self.running && count < frames {
+            self.update();
+            count += 1;
+        }
+        count
+    }
+
+    /// Delta of the frame the input playback applies next, so that the
+    /// fixed updates run on the recorded time as well
+    fn playback_delta(&self) -> Option<Duration> {
+        self.world
+            .try_fetch::<InputPlayer>()
+            .and_then(|player| player.next_delta())
+    }
+
+    fn advance_time(&mut self, delta: Duration) {
+        let mut time = self.world.write_resource::<Time>();
+        time.advance(delta);
+
+        *self.world.write_resource::<RunningTime>() = RunningTime(time.elapsed());
+        *self.world.write_resource::<DeltaTime>() = DeltaTime(time.delta());
+    }
+
+    /// Runs the fixed updates and the frame schedule, then handles the close
+    /// requests. Returns the time spent.
+    fn run_frame(&mut self) -> Duration {
+        let frame_start = Instant::now();
+
+        self.run_fixed_updates();
+
+        self.schedule.frame_dispatcher.dispatch(&self.world);
+        for schedule in self.layers.iter_mut().filter_map(|l| l.schedule.as_mut()) {
+            schedule.frame_dispatcher.dispatch(&self.world);
+        }
+
+        let frame_duration = Instant::now() - frame_start;
+
+        if let Some(receiver) = &self.close_receiver {
+            match receiver.try_recv() {
+                Ok(event) => {
+                    let mut channel = self
+                        .world
+                        .write_resource::<EventChannel<AppCloseRequestedEvent>>();
+                    channel.single_write(event)
+                }
+                // A handler that went away is not a close request
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => {}
             }
+        }
+
+        self.world.maintain();
+
+        let layer_drivers = self
+            .layers
+            .iter_mut()
+            .flat_map(|l| l.state_drivers.iter_mut());
+        for driver in self.state_drivers.iter_mut().chain(layer_drivers) {
+            driver.apply(&mut self.world);
+        }
 
-            {
-                match rx.try_recv() {
-                    Ok(event) => {
-                        let mut channel = self
-                            .world
-                            .write_resource::<EventChannel<AppCloseRequestedEvent>>();
-                        channel.single_write(event)
-                    }
-                    Err(err) => match err {
-                        TryRecvError::Disconnected => self.running = false,
-                        TryRecvError::Empty => {}
-                    },
+        console::run_pending(&mut self.world);
+
+        self.handle_system_errors();
+
+        {
+            let close_requested = self
+                .world
+                .read_resource::<EventChannel<AppCloseRequestedEvent>>()
+                .read(self.app_close_reader.as_mut().unwrap())
+                .next()
+                .is_some();
+
+            let mut control = self.world.write_resource::<CloseControl>();
+            if close_requested && !control.is_close_requested() {
+                control.request_close();
+                if control.is_held() {
+                    core_info!(
+                        "Close requested, waiting for: {}",
+                        control.holds().collect::<Vec<_>>().join(", ")
+                    );
                 }
             }
 
+            if control.should_close() {
+                self.running = false;
+            }
+        }
+
+        self.update_focus();
+
+        frame_duration
+    }
+
+    /// Applies the error policies to the errors sent since the last call
+    fn handle_system_errors(&mut self) {
+        let errors = self.world.read_resource::<EventChannel<SystemError>>();
+        let mut disabled = self.world.write_resource::<DisabledSystems>();
+
+        for error in errors.read(self.system_error_reader.as_mut().unwrap()) {
+            match self.error_policies.handle(error, &mut self.failure_log) {
+                ErrorAction::Continue => {}
+                ErrorAction::DisableSystem => disabled.disable(&error.system),
+                ErrorAction::StopApp => self.running = false,
+            }
+        }
+    }
+
+    fn update_focus(&mut self) {
+        let focus_channel = self
+            .world
+            .read_resource::<EventChannel<WindowFocusChanged>>();
+        if let Some(event) = focus_channel
+            .read(self.focus_reader.as_mut().unwrap())
+            .next_back()
+        {
+            self.frame_pacer.focused = event.focused;
+        }
+    }
+
+    fn run_fixed_updates(&mut self) {
+        let delta_time = self.world.read_resource::<DeltaTime>().0;
+        let steps = self.fixed_timestep.advance(delta_time);
+
+        for _ in 0..steps {
+            self.schedule.fixed_dispatcher.dispatch(&self.world);
+            for schedule in self.layers.iter_mut().filter_map(|l| l.schedule.as_mut()) {
+                schedule.fixed_dispatcher.dispatch(&self.world);
+            }
             self.world.maintain();
+        }
+
+        self.world.write_resource::<FixedTimestepAlpha>().0 = self.fixed_timestep.alpha();
+    }
+
+    /// Attaches a layer to a running app. Its systems get a schedule of their
+    /// own that runs after the app schedule, and its setup systems run
+    /// right away. They can be ordered after the systems of the app and of
+    /// the layers pushed before, but not before them.
+    pub fn push_layer<L>(&mut self, layer: L) -> Result<(), LayerError>
+    where
+        L: Layer + 'static,
+    {
+        self.init();
+
+        let mut layer = AttachedLayer::new(layer);
+        validate_dependencies(&layer, &self.layers)?;
+
+        core_debug!("App: pushing layer {}", layer.type_name);
+
+        let profiler = Profiler::clone(&self.world.read_resource::<Profiler>());
+        let mut schedule_builder = ScheduleBuilder::with_profiler(profiler);
+        schedule_builder.with_preceding_schedule(&self.schedule.info);
+        for schedule in self.layers.iter().filter_map(|l| l.schedule.as_ref()) {
+            schedule_builder.with_preceding_schedule(&schedule.info);
+        }
+
+        let mut app_builder = AppBuilder {
+            schedule_builder,
+            world: std::mem::take(&mut self.world),
+            ..Default::default()
+        };
+
+        let result = layer.layer.on_attach(&mut app_builder);
+        self.world = app_builder.world;
+
+        if let Err(err) = result {
+            return Err(LayerError::AttachFailed {
+                layer: layer.type_name,
+                source: err,
+            });
+        }
+
+        let mut schedule = app_builder.schedule_builder.build()?;
+        let state_drivers = app_builder
+            .state_schedules
+            .into_iter()
+            .map(|(_, schedules)| schedules.build())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        schedule.setup_dispatcher.setup(&mut self.world);
+        schedule.setup_dispatcher.dispatch(&self.world);
+        self.world.maintain();
 
-            {
-                let events = self
-                    .world
-                    .read_resource::<EventChannel<AppCloseRequestedEvent>>()
-                    .read(self.app_close_reader.as_mut().unwrap())
-                    .cloned()
-                    .collect::<Vec<_>>();
-
-                if !events.is_empty() {
-                    self.running = false;
-                    break;
+        schedule.fixed_dispatcher.setup(&mut self.world);
+        schedule.frame_dispatcher.setup(&mut self.world);
+        schedule.shutdown_dispatcher.setup(&mut self.world);
+
+        // A state type the app already drives keeps a single driver, so
+        // that the transitions run the systems of the layer too
+        for mut driver in state_drivers {
+            let existing = self
+                .state_drivers
+                .iter_mut()
+                .chain(
+                    self.layers
+                        .iter_mut()
+                        .flat_map(|l| l.state_drivers.iter_mut()),
+                )
+                .find(|existing| existing.state_type() == driver.state_type());
+
+            match existing {
+                Some(existing) => existing.merge_layer(layer.type_name, driver, &mut self.world),
+                None => {
+                    driver.init(&mut self.world);
+                    layer.state_drivers.push(driver);
                 }
             }
+        }
 
-            if sleep_duration > Duration::from_millis(0) {
-                std::thread::sleep(sleep_duration);
-            }
+        layer.schedule = Some(schedule);
+        self.layers.push(layer);
+
+        Ok(())
+    }
+
+    /// Detaches the last layer pushed with [`App::push_layer`] and removes its
+    /// systems. Layers attached by the [`AppBuilder`] stay until the app is
+    /// destroyed, since their systems are part of the app schedule.
+    pub fn pop_layer(&mut self) -> Option<Box<dyn Layer>> {
+        // Only runtime layers have a schedule of their own
+        self.layers.last()?.schedule.as_ref()?;
+
+        let mut layer = self.layers.pop()?;
+        core_debug!("App: popping layer {}", layer.type_name);
+
+        let layer_drivers = self
+            .layers
+            .iter_mut()
+            .flat_map(|l| l.state_drivers.iter_mut());
+        for driver in self.state_drivers.iter_mut().chain(layer_drivers) {
+            driver.remove_layer(layer.type_name);
         }
 
-        self.destroy();
+        self.detach(&mut layer);
+        Some(layer.layer)
+    }
 
-        logging_thread_join();
+    /// Runs the shutdown schedules of the runtime layers, newest first, then
+    /// the one of the app. Each runs only once, even if the app is destroyed
+    /// again.
+    fn run_shutdown(&mut self) {
+        core_debug!("App: running the shutdown schedule");
+
+        let empty = || DispatcherBuilder::new().build();
+
+        for schedule in self
+            .layers
+            .iter_mut()
+            .rev()
+            .filter_map(|l| l.schedule.as_mut())
+        {
+            std::mem::replace(&mut schedule.shutdown_dispatcher, empty()).dispatch(&self.world);
+            self.world.maintain();
+        }
+
+        std::mem::replace(&mut self.schedule.shutdown_dispatcher, empty()).dispatch(&self.world);
+        self.world.maintain();
+    }
+
+    fn detach(&mut self, layer: &mut AttachedLayer) {
+        layer.schedule = None;
+        if let Err(err) = layer.layer.on_detach(&mut self.world) {
+            core_error!("Failed to detach layer {}: {err:?}", layer.type_name);
+        }
+        self.world.maintain();
     }
 
-    fn destroy(&mut self) {
+    pub fn destroy(&mut self) {
         core_info!("Destroying \"{}\" application", self.name);
+
+        if self.initialized {
+            self.run_shutdown();
+        }
+
+        while let Some(mut layer) = self.layers.pop() {
+            core_debug!("App: detaching layer {}", layer.type_name);
+            self.detach(&mut layer);
+        }
+
+        log_span_stats(CORE_LOGGER_NAME);
+
+        if let Some(path) = &self.chrome_trace_path {
+            let profiler = self
+                .world
+                .entry::<Profiler>()
+                .or_insert_with(Profiler::default);
+            match profiler.write_chrome_trace(path) {
+                Ok(()) => core_info!("Wrote the system trace to {:?}", path),
+                Err(err) => core_error!("Failed to write the system trace to {:?}: {}", path, err),
+            }
+        }
+
         self.running = false;
     }
 
@@ -138,3 +584,374 @@ impl App {
         AppBuilder::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bizarre_common::resources::{DeltaTime, RunningTime};
+    use specs::{shrev::EventChannel, Read, System, WorldExt, Write};
+
+    use crate::{
+        app_builder::AppBuilder,
+        app_events::AppCloseRequestedEvent,
+        close_control::CloseControl,
+        console::Console,
+        debug_stats::DebugStats,
+        frame_pacing::FramePacing,
+        input::{InputPlayer, InputRecording, RecordedFrame},
+        layer::{Layer, LayerDependency, LayerError},
+        profiler::Profiler,
+        schedule::{ScheduleError, ScheduleType, SystemConfig},
+        App,
+    };
+
+    #[derive(Default)]
+    struct FrameCount(usize);
+
+    struct CountingSystem {
+        close_after: Option<usize>,
+    }
+
+    impl<'a> System<'a> for CountingSystem {
+        type SystemData = (
+            Write<'a, FrameCount>,
+            Write<'a, EventChannel<AppCloseRequestedEvent>>,
+        );
+
+        fn run(&mut self, (mut count, mut close_events): Self::SystemData) {
+            count.0 += 1;
+            if self.close_after == Some(count.0) {
+                close_events.single_write(AppCloseRequestedEvent);
+            }
+        }
+    }
+
+    fn build_app(close_after: Option<usize>) -> App {
+        App::builder()
+            .name("Headless test")
+            .isolated()
+            .with_frame_pacing(FramePacing::Uncapped)
+            .with_system(
+                ScheduleType::Frame,
+                CountingSystem { close_after },
+                "counting_system",
+                &[],
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn run_frames_updates_world() {
+        let mut app = build_app(None);
+        let mut other = build_app(None);
+        app.world().read_resource::<Profiler>().set_enabled(true);
+
+        assert_eq!(app.run_frames(3), 3);
+        assert_eq!(other.run_frames(1), 1);
+
+        assert_eq!(app.world().read_resource::<FrameCount>().0, 3);
+        assert_eq!(other.world().read_resource::<FrameCount>().0, 1);
+        assert!(app
+            .world()
+            .read_resource::<DebugStats>()
+            .system_times
+            .contains_key("counting_system"));
+
+        app.world_mut().write_resource::<FrameCount>().0 = 10;
+        app.update();
+        assert_eq!(app.world().read_resource::<FrameCount>().0, 11);
+    }
+
+    #[test]
+    fn apps_made_without_the_builder_run() {
+        let mut app = App::new("Bare app");
+        app.frame_pacing = FramePacing::Uncapped;
+
+        app.update();
+        app.step(Duration::from_millis(10));
+
+        assert!(app.is_running());
+        assert!(app
+            .world()
+            .read_resource::<Console>()
+            .command("help")
+            .is_some());
+    }
+
+    #[test]
+    fn step_advances_time_by_delta() {
+        let mut app = build_app(None);
+
+        app.step(Duration::from_millis(10));
+        app.step(Duration::from_millis(15));
+
+        assert_eq!(
+            app.world().read_resource::<RunningTime>().0,
+            Duration::from_millis(25)
+        );
+    }
+
+    #[derive(Default)]
+    struct SeenDeltas(Vec<Duration>);
+
+    struct DeltaRecorder;
+
+    impl<'a> System<'a> for DeltaRecorder {
+        type SystemData = (Read<'a, DeltaTime>, Write<'a, SeenDeltas>);
+
+        fn run(&mut self, (delta, mut seen): Self::SystemData) {
+            seen.0.push(delta.0);
+        }
+    }
+
+    #[test]
+    fn update_and_step_advance_time_before_the_frame() {
+        let build = || {
+            App::builder()
+                .name("Headless test")
+                .isolated()
+                .with_frame_pacing(FramePacing::TargetFps(200))
+                .with_system(ScheduleType::Frame, DeltaRecorder, "delta_recorder", &[])
+                .build()
+                .unwrap()
+        };
+
+        let mut stepped = build();
+        stepped.step(Duration::from_millis(10));
+        stepped.step(Duration::from_millis(15));
+        assert_eq!(
+            stepped.world().read_resource::<SeenDeltas>().0,
+            [Duration::from_millis(10), Duration::from_millis(15)]
+        );
+
+        let mut updated = build();
+        updated.run_frames(2);
+        let seen = &updated.world().read_resource::<SeenDeltas>().0;
+        assert_eq!(seen[0], Duration::ZERO);
+        assert!(seen[1] >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn update_advances_time_by_the_played_back_delta() {
+        let mut app = App::builder()
+            .name("Headless test")
+            .isolated()
+            .with_frame_pacing(FramePacing::Uncapped)
+            .with_system(ScheduleType::Frame, DeltaRecorder, "delta_recorder", &[])
+            .build()
+            .unwrap();
+
+        let delta = Duration::from_millis(40);
+        let frames = vec![RecordedFrame {
+            delta,
+            inputs: Vec::new(),
+        }];
+        app.world_mut()
+            .insert(InputPlayer::new(InputRecording { frames }));
+
+        app.update();
+        assert_eq!(app.world().read_resource::<SeenDeltas>().0, [delta]);
+        assert_eq!(app.world().read_resource::<RunningTime>().0, delta);
+    }
+
+    #[test]
+    fn close_request_stops_the_app() {
+        let mut app = build_app(Some(2));
+
+        assert_eq!(app.run_frames(10), 2);
+        assert!(!app.is_running());
+    }
+
+    #[test]
+    fn disconnected_termination_handler_does_not_stop_the_app() {
+        let mut app = build_app(None);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        app.close_receiver = Some(receiver);
+        drop(sender);
+
+        assert_eq!(app.run_frames(3), 3);
+        assert!(app.is_running());
+    }
+
+    #[derive(Default)]
+    struct DetachedLayers(Vec<&'static str>);
+
+    struct CountingLayer;
+
+    impl Layer for CountingLayer {
+        fn on_attach(&mut self, app_builder: &mut AppBuilder) -> anyhow::Result<()> {
+            app_builder.add_system(
+                ScheduleType::Frame,
+                CountingSystem { close_after: None },
+                "layer_counting_system",
+                &[],
+            );
+            Ok(())
+        }
+
+        fn on_detach(&mut self, world: &mut specs::World) -> anyhow::Result<()> {
+            world
+                .entry::<DetachedLayers>()
+                .or_insert_with(DetachedLayers::default)
+                .0
+                .push("counting");
+            Ok(())
+        }
+    }
+
+    struct DependentLayer;
+
+    impl Layer for DependentLayer {
+        fn on_detach(&mut self, world: &mut specs::World) -> anyhow::Result<()> {
+            world
+                .entry::<DetachedLayers>()
+                .or_insert_with(DetachedLayers::default)
+                .0
+                .push("dependent");
+            Ok(())
+        }
+
+        fn dependencies(&self) -> Vec<LayerDependency> {
+            vec![LayerDependency::of::<CountingLayer>()]
+        }
+    }
+
+    #[test]
+    fn layers_are_validated_and_detached_in_reverse() {
+        let result = App::builder()
+            .name("Headless test")
+            .isolated()
+            .with_layer(DependentLayer)
+            .with_layer(CountingLayer)
+            .build();
+        assert!(result.is_err());
+
+        let mut app = App::builder()
+            .name("Headless test")
+            .isolated()
+            .with_frame_pacing(FramePacing::Uncapped)
+            .with_layer(CountingLayer)
+            .with_layer(DependentLayer)
+            .build()
+            .unwrap();
+
+        app.destroy();
+        assert_eq!(
+            app.world().read_resource::<DetachedLayers>().0,
+            ["dependent", "counting"]
+        );
+    }
+
+    #[test]
+    fn runtime_layers_can_be_pushed_and_popped() {
+        let mut app = build_app(None);
+
+        app.run_frames(1);
+        app.push_layer(CountingLayer).unwrap();
+        assert!(app.push_layer(CountingLayer).is_err());
+
+        app.run_frames(2);
+        assert_eq!(app.world().read_resource::<FrameCount>().0, 5);
+
+        assert!(app.pop_layer().is_some());
+        assert!(app.pop_layer().is_none());
+
+        app.run_frames(1);
+        assert_eq!(app.world().read_resource::<FrameCount>().0, 6);
+        assert_eq!(
+            app.world().read_resource::<DetachedLayers>().0,
+            ["counting"]
+        );
+    }
+
+    struct OrderedLayer(SystemConfig);
+
+    impl Layer for OrderedLayer {
+        fn on_attach(&mut self, app_builder: &mut AppBuilder) -> anyhow::Result<()> {
+            app_builder.add_configured_system(
+                ScheduleType::Frame,
+                CountingSystem { close_after: None },
+                self.0.clone(),
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn runtime_layers_are_ordered_against_the_app_schedule() {
+        let mut app = build_app(None);
+
+        app.push_layer(OrderedLayer(
+            SystemConfig::new("after_app").after("counting_system"),
+        ))
+        .unwrap();
+        app.run_frames(1);
+        assert_eq!(app.world().read_resource::<FrameCount>().0, 2);
+        app.pop_layer();
+
+        assert!(matches!(
+            app.push_layer(OrderedLayer(
+                SystemConfig::new("before_app").before("counting_system")
+            )),
+            Err(LayerError::Schedule(
+                ScheduleError::BeforePrecedingSchedule { .. }
+            ))
+        ));
+    }
+
+    struct HoldUntil(usize);
+
+    impl<'a> System<'a> for HoldUntil {
+        type SystemData = (Write<'a, FrameCount>, Write<'a, CloseControl>);
+
+        fn run(&mut self, (count, mut control): Self::SystemData) {
+            if count.0 < self.0 {
+                control.hold("unsaved changes");
+            } else {
+                control.release("unsaved changes");
+            }
+        }
+    }
+
+    #[test]
+    fn close_waits_for_holds_and_runs_shutdown_once() {
+        let mut app = App::builder()
+            .name("Headless test")
+            .isolated()
+            .with_frame_pacing(FramePacing::Uncapped)
+            .with_system(
+                ScheduleType::Frame,
+                CountingSystem {
+                    close_after: Some(1),
+                },
+                "counting_system",
+                &[],
+            )
+            .with_system(
+                ScheduleType::Frame,
+                HoldUntil(3),
+                "hold",
+                &["counting_system"],
+            )
+            .with_system(
+                ScheduleType::Shutdown,
+                CountingSystem { close_after: None },
+                "shutdown_counting",
+                &[],
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(app.run_frames(10), 3);
+        assert!(app
+            .world()
+            .read_resource::<CloseControl>()
+            .is_close_requested());
+
+        app.destroy();
+        app.destroy();
+        assert_eq!(app.world().read_resource::<FrameCount>().0, 4);
+    }
+}
@@ -1,15 +1,36 @@
-use std::{default, marker::PhantomData, sync::Once};
+use std::{
+    any::TypeId,
+    default,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::Result;
+use bizarre_common::system_error::TrySystem;
 use bizarre_logger::{
-    core_critical, core_debug, core_info, global_loggers::logging_thread_start, logger_impl::Logger,
+    core_critical, core_debug, core_error, core_info, core_warn,
+    global_loggers::{
+        set_global_min_level, try_logging_thread_start, try_logging_thread_start_with_config,
+    },
+    logger_impl::Logger,
+    LogLevel,
 };
 use specs::{World, WorldExt};
 
 use crate::{
     app_events::AppCloseRequestedEvent,
-    layer::Layer,
-    schedule::{ScheduleBuilder, ScheduleType},
+    app_state::{
+        AppState, NextState, StateScheduleBuilder, StateSchedules, StateTransitionKind, States,
+    },
+    console::{CVarRegistry, CVarValue, Console},
+    engine_config::EngineConfig,
+    error_policy::{ErrorPolicies, ErrorPolicy, FailureLog},
+    fixed_timestep::FixedTimestep,
+    frame_pacing::{FramePacer, FramePacing},
+    layer::{validate_dependencies, AttachedLayer, Layer, LayerError},
+    schedule::{ScheduleBuilder, ScheduleType, Stage, SystemConfig},
+    time::{register_time_commands, TimerSystem},
     App,
 };
 
@@ -28,24 +49,43 @@ pub struct AppBuilder {
     pub name: Option<Box<str>>,
     pub schedule_builder: ScheduleBuilder,
     pub world: specs::World,
-    pub layers: Vec<(Box<dyn Layer>, &'static str)>,
+    pub layers: Vec<AttachedLayer>,
+    pub log_config: Option<PathBuf>,
+    pub fixed_timestep: FixedTimestep,
+    pub frame_pacing: FramePacing,
+    pub chrome_trace: Option<PathBuf>,
+    pub profiling: bool,
+    pub error_policies: ErrorPolicies,
+    /// Loaded from the config file when the app is built, before the layers
+    /// are attached
+    pub config: EngineConfig,
+    pub config_path: Option<PathBuf>,
+    pub config_args: Vec<String>,
+    /// See [`AppBuilder::isolated`]
+    pub isolated: bool,
+    pub(crate) state_schedules: Vec<(TypeId, Box<dyn StateScheduleBuilder>)>,
 }
 
-#[cfg(debug_assertions)]
-static APP_BUILDER_ONCE: Once = Once::new();
+/// Overrides the engine config path given to the builder
+pub const ENGINE_CONFIG_ENV_VAR: &str = "BIZARRE_CONFIG";
 
-impl AppBuilder {
-    pub fn new() -> AppBuilder {
-        #[cfg(debug_assertions)]
-        {
-            if APP_BUILDER_ONCE.is_completed() {
-                panic!("Cannot build application more than once!");
-            }
-            APP_BUILDER_ONCE.call_once(|| {});
-        }
+/// Looked up in the working directory when no engine config is given
+const DEFAULT_ENGINE_CONFIG_PATHS: [&str; 2] = ["engine.toml", "engine.ron"];
+
+/// Log config path used when the builder isn't given one
+pub const LOG_CONFIG_ENV_VAR: &str = "BIZARRE_LOG_CONFIG";
+
+/// Overrides the Chrome trace path given to the builder
+pub const CHROME_TRACE_ENV_VAR: &str = "BIZARRE_CHROME_TRACE";
 
-        logging_thread_start(None);
+/// The schedule graph is written to this path in the DOT format when set
+pub const SCHEDULE_DOT_ENV_VAR: &str = "BIZARRE_SCHEDULE_DOT";
 
+/// Looked up in the working directory when no log config is given
+const DEFAULT_LOG_CONFIG_PATHS: [&str; 2] = ["logging.toml", "logging.ron"];
+
+impl AppBuilder {
+    pub fn new() -> AppBuilder {
         AppBuilder {
             world: specs::World::new(),
             ..Default::default()
@@ -57,12 +97,184 @@ impl AppBuilder {
         self
     }
 
+    /// Builds the app without touching its surroundings, for tests: the
+    /// config files in the working directory and the `BIZARRE_*` variables
+    /// are ignored, and the logger thread with its file loggers isn't
+    /// started, so the logs go to stderr. Paths given to the builder are
+    /// still used.
+    pub fn isolated(mut self) -> Self {
+        self.isolated = true;
+        self
+    }
+
+    /// Engine settings are loaded from this `.toml` or `.ron` file when the
+    /// app is built. `BIZARRE_CONFIG` takes precedence over it.
+    pub fn with_config<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.config_path = Some(path.as_ref().into());
+        self
+    }
+
+    /// Default for a config key, the config file and the command line take
+    /// precedence over it
+    pub fn with_config_value<V: Into<toml::Value>>(mut self, key: &str, value: V) -> Self {
+        self.config.set(key, value);
+        self
+    }
+
+    /// Command line arguments, the `--set key=value` ones override the
+    /// config file
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Submits the lines typed into the terminal to the [`Console`], same as
+    /// setting `console.stdin` in the engine config
+    pub fn with_stdin_console(self) -> Self {
+        self.with_config_value("console.stdin", true)
+    }
+
+    /// Loggers are built from this `.toml` or `.ron` file when the app is
+    /// built. It takes precedence over `BIZARRE_LOG_CONFIG`, which takes
+    /// precedence over the `log.config` key of the engine config.
+    pub fn with_log_config<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.log_config = Some(path.as_ref().into());
+        self
+    }
+
+    /// Step of the [`ScheduleType::FixedUpdate`] dispatcher
+    pub fn with_fixed_timestep(mut self, step: Duration) -> Self {
+        self.fixed_timestep.set_step(step);
+        self
+    }
+
+    /// Upper bound of fixed updates run in a single frame
+    pub fn with_max_fixed_substeps(mut self, max_substeps: u32) -> Self {
+        self.fixed_timestep.set_max_substeps(max_substeps);
+        self
+    }
+
+    /// Records the run times of the systems in the [`crate::profiler::Profiler`],
+    /// shown by [`crate::debug_stats::DebugStats`]. A Chrome trace path turns
+    /// it on too.
+    pub fn with_profiling(mut self) -> Self {
+        self.profiling = true;
+        self
+    }
+
+    /// Writes the system trace of the profiler to this path when the app is
+    /// destroyed. See [`crate::profiler::Profiler::write_chrome_trace`] for
+    /// writing it on demand.
+    pub fn with_chrome_trace<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.chrome_trace = Some(path.as_ref().into());
+        self
+    }
+
+    pub fn with_frame_pacing(mut self, frame_pacing: FramePacing) -> Self {
+        self.frame_pacing = frame_pacing;
+        self
+    }
+
+    /// Policy for the errors of fallible systems without a policy of their
+    /// own. Errors are only logged by default.
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policies.default = policy;
+        self
+    }
+
+    pub fn with_system_error_policy(mut self, system: &str, policy: ErrorPolicy) -> Self {
+        self.error_policies.systems.insert(system.into(), policy);
+        self
+    }
+
+    /// Inserts the [`AppState`] and [`NextState`] resources. The on-enter
+    /// systems of `initial` run when the app is initialized.
+    pub fn with_state<S: States>(mut self, initial: S) -> Self {
+        self.world.insert(AppState::new(initial));
+        self.world.insert(NextState::<S>::default());
+        self.state_schedules_mut::<S>();
+        self
+    }
+
+    fn state_schedules_mut<S: States>(&mut self) -> &mut StateSchedules<S> {
+        let type_id = TypeId::of::<S>();
+
+        let index = match self
+            .state_schedules
+            .iter()
+            .position(|(id, _)| *id == type_id)
+        {
+            Some(index) => index,
+            None => {
+                let profiler = self.schedule_builder.profiler().clone();
+                self.state_schedules
+                    .push((type_id, Box::new(StateSchedules::<S>::new(profiler))));
+                self.state_schedules.len() - 1
+            }
+        };
+
+        self.state_schedules[index]
+            .1
+            .as_any_mut()
+            .downcast_mut()
+            .expect("State schedules are stored by the type id of their state")
+    }
+
+    /// Runs the system every time the app enters `state`
+    pub fn add_on_enter<S, Sys>(&mut self, state: S, system: Sys, config: SystemConfig)
+    where
+        S: States,
+        Sys: for<'a> specs::System<'a> + 'static + Send,
+    {
+        self.state_schedules_mut::<S>().add_system(
+            StateTransitionKind::Enter,
+            state,
+            system,
+            config,
+        );
+    }
+
+    pub fn with_on_enter<S, Sys>(mut self, state: S, system: Sys, config: SystemConfig) -> Self
+    where
+        S: States,
+        Sys: for<'a> specs::System<'a> + 'static + Send,
+    {
+        self.add_on_enter(state, system, config);
+        self
+    }
+
+    /// Runs the system every time the app leaves `state`
+    pub fn add_on_exit<S, Sys>(&mut self, state: S, system: Sys, config: SystemConfig)
+    where
+        S: States,
+        Sys: for<'a> specs::System<'a> + 'static + Send,
+    {
+        self.state_schedules_mut::<S>().add_system(
+            StateTransitionKind::Exit,
+            state,
+            system,
+            config,
+        );
+    }
+
+    pub fn with_on_exit<S, Sys>(mut self, state: S, system: Sys, config: SystemConfig) -> Self
+    where
+        S: States,
+        Sys: for<'a> specs::System<'a> + 'static + Send,
+    {
+        self.add_on_exit(state, system, config);
+        self
+    }
+
     pub fn with_layer<L>(mut self, layer: L) -> Self
     where
         L: Layer + 'static,
     {
-        self.layers
-            .push((Box::new(layer), std::any::type_name::<L>()));
+        self.layers.push(AttachedLayer::new(layer));
         self
     }
 
@@ -93,12 +305,59 @@ impl AppBuilder {
         self
     }
 
+    pub fn add_configured_system<S>(
+        &mut self,
+        schedule_type: ScheduleType,
+        system: S,
+        config: SystemConfig,
+    ) where
+        S: for<'a> specs::System<'a> + 'static + Send,
+    {
+        self.schedule_builder
+            .with_configured_system(schedule_type, system, config);
+    }
+
+    pub fn with_configured_system<S>(
+        mut self,
+        schedule_type: ScheduleType,
+        system: S,
+        config: SystemConfig,
+    ) -> Self
+    where
+        S: for<'a> specs::System<'a> + 'static + Send,
+    {
+        self.add_configured_system(schedule_type, system, config);
+        self
+    }
+
+    /// Adds a system returning errors, see [`TrySystem`]
+    pub fn add_try_system<S>(
+        &mut self,
+        schedule_type: ScheduleType,
+        system: S,
+        config: SystemConfig,
+    ) where
+        S: for<'a> TrySystem<'a> + 'static + Send,
+    {
+        self.schedule_builder
+            .with_try_system(schedule_type, system, config);
+    }
+
+    pub fn with_try_system<S>(
+        mut self,
+        schedule_type: ScheduleType,
+        system: S,
+        config: SystemConfig,
+    ) -> Self
+    where
+        S: for<'a> TrySystem<'a> + 'static + Send,
+    {
+        self.add_try_system(schedule_type, system, config);
+        self
+    }
+
     pub fn add_barrier(&mut self, schedule_type: ScheduleType) {
-        let dispatcher = match schedule_type {
-            ScheduleType::Frame => self.schedule_builder.frame_dispatcher.as_mut().unwrap(),
-            ScheduleType::Setup => self.schedule_builder.setup_dispatcher.as_mut().unwrap(),
-        };
-        dispatcher.add_barrier();
+        self.schedule_builder.add_barrier(schedule_type);
     }
 
     pub fn with_barrier(mut self, schedule_type: ScheduleType) -> Self {
@@ -112,27 +371,179 @@ impl AppBuilder {
             .take()
             .expect("Cannot create an app without a name");
 
-        core_info!("Started the logger thread!");
+        let config_path = self
+            .env_path(ENGINE_CONFIG_ENV_VAR)
+            .or(self.config_path.take())
+            .or_else(|| self.find_default_path(&DEFAULT_ENGINE_CONFIG_PATHS));
+
+        if let Some(path) = &config_path {
+            self.config.merge(EngineConfig::load(path)?);
+        }
+        self.config.apply_args(&self.config_args)?;
+
+        let log_config = self
+            .log_config
+            .take()
+            .or_else(|| self.env_path(LOG_CONFIG_ENV_VAR))
+            .or_else(|| self.config.get_or::<Option<PathBuf>>("log.config", None))
+            .or_else(|| self.find_default_path(&DEFAULT_LOG_CONFIG_PATHS));
+
+        // Apps built after the first one share its logger thread
+        let logger_thread = match &log_config {
+            Some(path) => try_logging_thread_start_with_config(path),
+            None if self.isolated => None,
+            None => try_logging_thread_start(None),
+        };
+
+        if logger_thread.is_some() {
+            core_info!("Started the logger thread!");
+        }
+
+        if let Some(path) = &config_path {
+            core_info!("Loaded the engine config from {:?}", path);
+        }
+
+        // Layers register their commands and variables while attached
+        let mut console = engine_console();
+        if self.config.get_or("console.stdin", false) {
+            console.attach_stdin();
+        }
+        self.world.insert(console);
+        self.world.insert(engine_cvars());
+
+        self.add_configured_system(
+            ScheduleType::Frame,
+            TimerSystem,
+            SystemConfig::new(TimerSystem::DEFAULT_NAME).stage(Stage::First),
+        );
 
         let layers = std::mem::take(&mut self.layers);
+        let mut attached = Vec::with_capacity(layers.len());
 
-        for (mut layer, type_name) in layers {
-            core_debug!("AppBuilder: attaching layer {}", type_name);
-            if let Err(err) = layer.on_attach(&mut self) {
-                let msg = format!("Failed to attach the layer: {err:?}");
-                core_critical!(msg);
-                panic!("{msg}");
+        for mut layer in layers {
+            validate_dependencies(&layer, &attached)?;
+
+            core_debug!("AppBuilder: attaching layer {}", layer.type_name);
+            if let Err(err) = layer.layer.on_attach(&mut self) {
+                let err = LayerError::AttachFailed {
+                    layer: layer.type_name,
+                    source: err,
+                };
+                core_critical!("{}", err);
+                return Err(err.into());
             }
+            attached.push(layer);
         }
 
         self.world.maintain();
 
+        for err in self
+            .world
+            .write_resource::<CVarRegistry>()
+            .apply_config(&self.config)
+        {
+            core_warn!("Engine config: {}", err);
+        }
+
+        self.world.insert(std::mem::take(&mut self.config));
+
+        self.world.insert(self.schedule_builder.profiler().clone());
+
+        let schedule = self.schedule_builder.build()?;
+
+        let state_drivers = std::mem::take(&mut self.state_schedules)
+            .into_iter()
+            .map(|(_, schedules)| schedules.build())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (schedule_type, conflict) in schedule.info.write_conflicts() {
+            let [a, b] = &conflict.systems;
+            core_warn!(
+                "{schedule_type:?} schedule: \"{a}\" and \"{b}\" write the same resource ({:?}) in no particular order",
+                conflict.resource
+            );
+        }
+
+        if let Some(path) = self.env_path(SCHEDULE_DOT_ENV_VAR) {
+            if let Err(err) = schedule.info.write_dot(&path) {
+                core_error!("Failed to write the schedule graph to {:?}: {}", path, err);
+            }
+        }
+
+        let chrome_trace_path = self
+            .env_path(CHROME_TRACE_ENV_VAR)
+            .or(self.chrome_trace.take());
+
+        let profiler = self.schedule_builder.profiler();
+        profiler.set_enabled(self.profiling || chrome_trace_path.is_some());
+        profiler.set_tracing(chrome_trace_path.is_some());
+
         Ok(App {
             world: self.world,
             name,
-            schedule: self.schedule_builder.build(),
+            schedule,
             running: false,
             app_close_reader: None,
+            fixed_timestep: self.fixed_timestep,
+            frame_pacing: self.frame_pacing,
+            frame_pacer: FramePacer::default(),
+            last_frame_start: None,
+            focus_reader: None,
+            close_receiver: None,
+            initialized: false,
+            layers: attached,
+            state_drivers,
+            error_policies: self.error_policies,
+            failure_log: FailureLog::default(),
+            system_error_reader: None,
+            chrome_trace_path,
         })
     }
+
+    /// Ignored when the builder is isolated
+    fn env_path(&self, var: &str) -> Option<PathBuf> {
+        if self.isolated {
+            None
+        } else {
+            std::env::var_os(var).map(PathBuf::from)
+        }
+    }
+
+    /// First of the paths found in the working directory, unless the builder
+    /// is isolated
+    fn find_default_path(&self, paths: &[&str]) -> Option<PathBuf> {
+        if self.isolated {
+            None
+        } else {
+            paths.iter().map(PathBuf::from).find(|path| path.is_file())
+        }
+    }
+}
+
+/// Console with the engine commands, before the layers add theirs
+pub(crate) fn engine_console() -> Console {
+    let mut console = Console::default();
+    register_time_commands(&mut console);
+    console
+}
+
+pub(crate) fn engine_cvars() -> CVarRegistry {
+    let mut cvars = CVarRegistry::default();
+
+    cvars.register(
+        "log.level",
+        "Debug",
+        "Messages below this level are dropped: Debug, Info, Warn, Error or Critical",
+    );
+    cvars
+        .on_change("log.level", |value| match value {
+            CVarValue::String(level) => {
+                set_global_min_level(level.parse::<LogLevel>()?);
+                Ok(())
+            }
+            _ => Err("expected a log level".into()),
+        })
+        .expect("log.level is registered above");
+
+    cvars
 }
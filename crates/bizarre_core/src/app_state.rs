@@ -0,0 +1,525 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+};
+
+use bizarre_logger::{core_debug, core_warn};
+use specs::{Dispatcher, ReadExpect, System, SystemData, World, WorldExt};
+
+use crate::{
+    profiler::Profiler,
+    schedule::{ScheduleBuilder, ScheduleError, ScheduleType, SystemConfig},
+};
+
+/// Requests made by on-enter and on-exit systems are applied right away, up
+/// to this many transitions per frame
+const MAX_TRANSITIONS_PER_FRAME: usize = 8;
+
+pub trait States: Copy + Eq + Hash + Debug + Send + Sync + 'static {}
+
+impl<T> States for T where T: Copy + Eq + Hash + Debug + Send + Sync + 'static {}
+
+/// Current state of the app, changed through [`NextState`]
+#[derive(Debug)]
+pub struct AppState<S: States> {
+    current: S,
+    previous: Option<S>,
+}
+
+impl<S: States> AppState<S> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            current: initial,
+            previous: None,
+        }
+    }
+
+    pub fn current(&self) -> S {
+        self.current
+    }
+
+    pub fn previous(&self) -> Option<S> {
+        self.previous
+    }
+
+    pub fn is(&self, state: S) -> bool {
+        self.current == state
+    }
+}
+
+/// Transition requests, kept apart from [`AppState`] so that systems running
+/// in a state can request the next one. Requests made during a frame are
+/// applied after it.
+#[derive(Debug)]
+pub struct NextState<S: States>(Option<S>);
+
+impl<S: States> Default for NextState<S> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<S: States> NextState<S> {
+    /// Overrides the earlier requests made in the same frame
+    pub fn request(&mut self, state: S) {
+        self.0 = Some(state);
+    }
+
+    pub fn pending(&self) -> Option<S> {
+        self.0
+    }
+}
+
+/// Runs the wrapped system only while the app is in one of the states
+pub struct RunInState<S: States, Sys> {
+    states: Vec<S>,
+    inner: Sys,
+}
+
+pub fn in_state<S: States, Sys>(state: S, system: Sys) -> RunInState<S, Sys> {
+    in_states(&[state], system)
+}
+
+pub fn in_states<S: States, Sys>(states: &[S], system: Sys) -> RunInState<S, Sys> {
+    RunInState {
+        states: states.to_vec(),
+        inner: system,
+    }
+}
+
+impl<'a, S, Sys> System<'a> for RunInState<S, Sys>
+where
+    S: States,
+    Sys: System<'a>,
+    Sys::SystemData: SystemData<'a>,
+{
+    type SystemData = (ReadExpect<'a, AppState<S>>, Sys::SystemData);
+
+    fn run(&mut self, (state, data): Self::SystemData) {
+        if self.states.contains(&state.current) {
+            self.inner.run(data);
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.inner.setup(world);
+    }
+}
+
+/// Runs the on-enter and on-exit systems of one state type
+pub trait StateTransitionDriver {
+    /// Sets up the systems and enters the initial state
+    fn init(&mut self, world: &mut World);
+
+    /// Applies the requested transitions, returns whether any happened
+    fn apply(&mut self, world: &mut World) -> bool;
+
+    fn state_type(&self) -> TypeId;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+
+    /// Takes over the systems of a runtime layer for the same state type,
+    /// then sets them up and enters the current state with them
+    fn merge_layer(
+        &mut self,
+        layer: &'static str,
+        driver: Box<dyn StateTransitionDriver>,
+        world: &mut World,
+    );
+
+    /// Drops the systems merged from the layer
+    fn remove_layer(&mut self, layer: &'static str);
+}
+
+struct StateDriver<S: States> {
+    on_enter: HashMap<S, Dispatcher<'static, 'static>>,
+    on_exit: HashMap<S, Dispatcher<'static, 'static>>,
+    /// Systems of the runtime layers for the same state type, run after
+    /// the ones of the driver
+    layers: Vec<(&'static str, StateDriver<S>)>,
+}
+
+impl<S: States> StateDriver<S> {
+    fn dispatch(
+        dispatchers: &mut HashMap<S, Dispatcher<'static, 'static>>,
+        state: S,
+        world: &mut World,
+    ) {
+        if let Some(dispatcher) = dispatchers.get_mut(&state) {
+            dispatcher.dispatch(world);
+            world.maintain();
+        }
+    }
+
+    fn enter(&mut self, state: S, world: &mut World) {
+        Self::dispatch(&mut self.on_enter, state, world);
+        for (_, driver) in &mut self.layers {
+            driver.enter(state, world);
+        }
+    }
+
+    fn exit(&mut self, state: S, world: &mut World) {
+        Self::dispatch(&mut self.on_exit, state, world);
+        for (_, driver) in &mut self.layers {
+            driver.exit(state, world);
+        }
+    }
+}
+
+impl<S: States> StateTransitionDriver for StateDriver<S> {
+    fn init(&mut self, world: &mut World) {
+        if !world.has_value::<AppState<S>>() {
+            core_warn!(
+                "AppState<{}> has state systems but no initial state",
+                std::any::type_name::<S>()
+            );
+            return;
+        }
+
+        for dispatcher in self.on_enter.values_mut().chain(self.on_exit.values_mut()) {
+            dispatcher.setup(world);
+        }
+
+        let initial = world.read_resource::<AppState<S>>().current;
+        self.enter(initial, world);
+    }
+
+    fn apply(&mut self, world: &mut World) -> bool {
+        let mut transitioned = false;
+
+        if !world.has_value::<AppState<S>>() {
+            return transitioned;
+        }
+
+        for _ in 0..MAX_TRANSITIONS_PER_FRAME {
+            let current = world.read_resource::<AppState<S>>().current;
+            let next = match world.write_resource::<NextState<S>>().0.take() {
+                Some(next) if next != current => next,
+                _ => return transitioned,
+            };
+
+            core_debug!("AppState: {:?} -> {:?}", current, next);
+
+            self.exit(current, world);
+            {
+                let mut state = world.write_resource::<AppState<S>>();
+                state.previous = Some(current);
+                state.current = next;
+            }
+            self.enter(next, world);
+
+            transitioned = true;
+        }
+
+        let current = world.read_resource::<AppState<S>>().current;
+        let pending = world.read_resource::<NextState<S>>().0;
+        if pending.is_some_and(|next| next != current) {
+            core_warn!(
+                "AppState<{}>: stopped after {} transitions in one frame",
+                std::any::type_name::<S>(),
+                MAX_TRANSITIONS_PER_FRAME
+            );
+        }
+        transitioned
+    }
+
+    fn state_type(&self) -> TypeId {
+        TypeId::of::<S>()
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn merge_layer(
+        &mut self,
+        layer: &'static str,
+        driver: Box<dyn StateTransitionDriver>,
+        world: &mut World,
+    ) {
+        let mut driver = *driver
+            .into_any()
+            .downcast::<StateDriver<S>>()
+            .expect("Only drivers of the same state type are merged");
+
+        driver.init(world);
+        self.layers.push((layer, driver));
+    }
+
+    fn remove_layer(&mut self, layer: &'static str) {
+        self.layers.retain(|(name, _)| *name != layer);
+    }
+}
+
+/// On-enter and on-exit systems of one state type collected by the
+/// [`crate::app_builder::AppBuilder`]
+pub(crate) trait StateScheduleBuilder {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn build(self: Box<Self>) -> Result<Box<dyn StateTransitionDriver>, ScheduleError>;
+}
+
+pub(crate) struct StateSchedules<S: States> {
+    profiler: Profiler,
+    on_enter: HashMap<S, ScheduleBuilder>,
+    on_exit: HashMap<S, ScheduleBuilder>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StateTransitionKind {
+    Enter,
+    Exit,
+}
+
+impl<S: States> StateSchedules<S> {
+    pub(crate) fn new(profiler: Profiler) -> Self {
+        Self {
+            profiler,
+            on_enter: HashMap::new(),
+            on_exit: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn add_system<Sys>(
+        &mut self,
+        kind: StateTransitionKind,
+        state: S,
+        system: Sys,
+        config: SystemConfig,
+    ) where
+        Sys: for<'a> System<'a> + 'static + Send,
+    {
+        let schedules = match kind {
+            StateTransitionKind::Enter => &mut self.on_enter,
+            StateTransitionKind::Exit => &mut self.on_exit,
+        };
+
+        schedules
+            .entry(state)
+            .or_insert_with(|| ScheduleBuilder::with_profiler(self.profiler.clone()))
+            .with_configured_system(ScheduleType::Frame, system, config);
+    }
+}
+
+impl<S: States> StateScheduleBuilder for StateSchedules<S> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn build(self: Box<Self>) -> Result<Box<dyn StateTransitionDriver>, ScheduleError> {
+        let build = |schedules: HashMap<S, ScheduleBuilder>| {
+            schedules
+                .into_iter()
+                .map(|(state, mut builder)| Ok((state, builder.build()?.frame_dispatcher)))
+                .collect::<Result<HashMap<_, _>, ScheduleError>>()
+        };
+
+        Ok(Box::new(StateDriver {
+            on_enter: build(self.on_enter)?,
+            on_exit: build(self.on_exit)?,
+            layers: Vec::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bizarre_logger::{
+        log_capture::{capture_logs, CapturedLogs},
+        LogLevel,
+    };
+    use specs::{System, Write};
+
+    use super::*;
+    use crate::{
+        app::App, app_builder::AppBuilder, frame_pacing::FramePacing, layer::Layer,
+        schedule::ScheduleType,
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum GameState {
+        Loading,
+        Playing,
+        Paused,
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    struct Push(&'static str);
+
+    impl<'a> System<'a> for Push {
+        type SystemData = Write<'a, Log>;
+
+        fn run(&mut self, mut log: Self::SystemData) {
+            log.0.push(self.0.into());
+        }
+    }
+
+    struct Request(GameState);
+
+    impl<'a> System<'a> for Request {
+        type SystemData = Write<'a, NextState<GameState>>;
+
+        fn run(&mut self, mut state: Self::SystemData) {
+            state.request(self.0);
+        }
+    }
+
+    #[test]
+    fn runs_enter_exit_and_state_systems() {
+        let mut app = App::builder()
+            .name("State test")
+            .isolated()
+            .with_frame_pacing(FramePacing::Uncapped)
+            .with_state(GameState::Loading)
+            .with_on_enter(
+                GameState::Loading,
+                Push("enter loading"),
+                SystemConfig::new("enter_loading"),
+            )
+            .with_on_exit(
+                GameState::Loading,
+                Push("exit loading"),
+                SystemConfig::new("exit_loading"),
+            )
+            .with_on_enter(
+                GameState::Playing,
+                Push("enter playing"),
+                SystemConfig::new("enter_playing"),
+            )
+            .with_system(
+                ScheduleType::Frame,
+                in_state(GameState::Loading, Request(GameState::Playing)),
+                "finish_loading",
+                &[],
+            )
+            .with_system(
+                ScheduleType::Frame,
+                in_state(GameState::Playing, Push("playing")),
+                "play",
+                &[],
+            )
+            .build()
+            .unwrap();
+
+        app.run_frames(3);
+
+        assert_eq!(
+            app.world().read_resource::<Log>().0,
+            [
+                "enter loading",
+                "exit loading",
+                "enter playing",
+                "playing",
+                "playing"
+            ]
+        );
+
+        let state = app.world().read_resource::<AppState<GameState>>();
+        assert!(state.is(GameState::Playing));
+        assert_eq!(state.previous(), Some(GameState::Loading));
+        assert!(!state.is(GameState::Paused));
+    }
+
+    struct StateLayer;
+
+    impl Layer for StateLayer {
+        fn on_attach(&mut self, app_builder: &mut AppBuilder) -> anyhow::Result<()> {
+            app_builder.add_on_enter(
+                GameState::Playing,
+                Push("layer enter playing"),
+                SystemConfig::new("layer_enter_playing"),
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn runtime_layers_join_the_driver_of_their_state() {
+        let mut app = App::builder()
+            .name("State test")
+            .isolated()
+            .with_frame_pacing(FramePacing::Uncapped)
+            .with_state(GameState::Loading)
+            .with_on_enter(
+                GameState::Playing,
+                Push("enter playing"),
+                SystemConfig::new("enter_playing"),
+            )
+            .build()
+            .unwrap();
+
+        let enter = |app: &mut App, state| {
+            app.world_mut()
+                .write_resource::<NextState<GameState>>()
+                .request(state);
+            app.run_frames(1);
+        };
+
+        app.push_layer(StateLayer).unwrap();
+        enter(&mut app, GameState::Playing);
+        enter(&mut app, GameState::Paused);
+
+        app.pop_layer().unwrap();
+        enter(&mut app, GameState::Playing);
+
+        assert_eq!(
+            app.world().read_resource::<Log>().0,
+            ["enter playing", "layer enter playing", "enter playing"]
+        );
+    }
+
+    /// Entering a state requests the next one, up to `last`
+    struct RequestNext(u8, u8);
+
+    impl<'a> System<'a> for RequestNext {
+        type SystemData = Write<'a, NextState<u8>>;
+
+        fn run(&mut self, mut state: Self::SystemData) {
+            if self.0 < self.1 {
+                state.request(self.0 + 1);
+            }
+        }
+    }
+
+    fn run_chain(last: u8) -> (u8, CapturedLogs) {
+        let mut builder = App::builder()
+            .name("State test")
+            .isolated()
+            .with_frame_pacing(FramePacing::Uncapped)
+            .with_state(0u8);
+        for state in 0..=last {
+            builder = builder.with_on_enter(
+                state,
+                RequestNext(state, last),
+                SystemConfig::new("request_next"),
+            );
+        }
+        let mut app = builder.build().unwrap();
+
+        app.init();
+        let logs = capture_logs(|| {
+            app.step(Duration::ZERO);
+        });
+        let current = app.world().read_resource::<AppState<u8>>().current();
+        (current, logs)
+    }
+
+    #[test]
+    fn warns_only_when_transitions_are_left_over() {
+        let limit = MAX_TRANSITIONS_PER_FRAME as u8;
+
+        let (current, logs) = run_chain(limit);
+        assert_eq!(current, limit);
+        assert!(logs.with_level(LogLevel::Warn).next().is_none());
+
+        let (current, logs) = run_chain(limit + 1);
+        assert_eq!(current, limit);
+        assert!(logs.contains(LogLevel::Warn, "stopped after"));
+    }
+}
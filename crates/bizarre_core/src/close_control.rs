@@ -0,0 +1,49 @@
+use std::collections::BTreeSet;
+
+/// Lets systems keep the app open after a close was requested, e.g. to ask
+/// about unsaved changes. The app closes at the end of the first frame in
+/// which a close is requested and nothing holds it open.
+#[derive(Debug, Default)]
+pub struct CloseControl {
+    requested: bool,
+    holds: BTreeSet<String>,
+}
+
+impl CloseControl {
+    pub fn is_close_requested(&self) -> bool {
+        self.requested
+    }
+
+    /// Same as sending an `AppCloseRequestedEvent`
+    pub fn request_close(&mut self) {
+        self.requested = true;
+    }
+
+    /// Cancels the pending close request
+    pub fn veto(&mut self) {
+        self.requested = false;
+    }
+
+    /// Keeps the app open until the hold is released. Holding with the same
+    /// reason twice needs a single release.
+    pub fn hold(&mut self, reason: &str) {
+        self.holds.insert(reason.into());
+    }
+
+    pub fn release(&mut self, reason: &str) {
+        self.holds.remove(reason);
+    }
+
+    pub fn holds(&self) -> impl Iterator<Item = &str> {
+        self.holds.iter().map(String::as_str)
+    }
+
+    pub fn is_held(&self) -> bool {
+        !self.holds.is_empty()
+    }
+
+    /// Whether the app should close at the end of this frame
+    pub(crate) fn should_close(&self) -> bool {
+        self.requested && self.holds.is_empty()
+    }
+}
@@ -0,0 +1,7 @@
+pub mod command;
+pub mod command_console;
+pub mod cvar;
+
+pub use command::*;
+pub use command_console::*;
+pub use cvar::*;
@@ -0,0 +1,144 @@
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+use anyhow::anyhow;
+use specs::World;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("unknown command or console variable '{0}'")]
+    Unknown(String),
+
+    #[error("unterminated quote in '{0}'")]
+    UnterminatedQuote(String),
+
+    #[error("{command}: {source} (usage: {usage})")]
+    Failed {
+        command: String,
+        usage: String,
+        source: anyhow::Error,
+    },
+}
+
+/// Runs a command with exclusive access to the world. The returned text is
+/// printed to the console.
+pub type CommandFn = dyn Fn(&mut World, &CommandArgs) -> anyhow::Result<String> + Send + Sync;
+
+#[derive(Clone)]
+pub struct Command {
+    /// Arguments after the name, e.g. `<name> [value]`
+    pub usage: String,
+    pub description: String,
+    pub(crate) handler: Arc<CommandFn>,
+}
+
+/// Arguments of a command, without its name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandArgs {
+    args: Vec<String>,
+}
+
+impl CommandArgs {
+    pub fn new(args: Vec<String>) -> Self {
+        Self { args }
+    }
+
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.args.get(index).map(String::as_str)
+    }
+
+    /// Fails with a message fit for the console when the argument is
+    /// missing or doesn't parse
+    pub fn parse<T>(&self, index: usize) -> anyhow::Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let arg = self
+            .get(index)
+            .ok_or_else(|| anyhow!("missing argument {}", index + 1))?;
+
+        arg.parse()
+            .map_err(|err| anyhow!("invalid argument {} '{}': {}", index + 1, arg, err))
+    }
+
+    /// Arguments from `index` on, joined by spaces
+    pub fn rest(&self, index: usize) -> String {
+        self.args.get(index..).unwrap_or_default().join(" ")
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.args.iter().map(String::as_str)
+    }
+}
+
+/// Splits a command line on whitespace. Double quotes group words and `\`
+/// escapes the next character.
+pub fn tokenize(line: &str) -> Result<Vec<String>, CommandError> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_token = false;
+    let mut quoted = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    token.push(escaped);
+                }
+                in_token = true;
+            }
+            '"' => {
+                quoted = !quoted;
+                in_token = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut token));
+                    in_token = false;
+                }
+            }
+            c => {
+                token.push(c);
+                in_token = true;
+            }
+        }
+    }
+
+    if quoted {
+        return Err(CommandError::UnterminatedQuote(line.into()));
+    }
+    if in_token {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_quotes_and_escapes() {
+        assert_eq!(
+            tokenize(r#"  echo "hello world" a\ b "" "#).unwrap(),
+            vec!["echo", "hello world", "a b", ""]
+        );
+        assert!(tokenize("echo \"open").is_err());
+
+        let args = CommandArgs::new(vec!["4".into(), "x".into()]);
+        assert_eq!(args.parse::<u32>(0).unwrap(), 4);
+        assert!(args.parse::<u32>(1).is_err());
+        assert!(args.parse::<u32>(2).is_err());
+    }
+}
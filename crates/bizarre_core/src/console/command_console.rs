@@ -0,0 +1,449 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::BufRead,
+    path::Path,
+    sync::{
+        mpsc::{channel, Receiver},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{anyhow, bail};
+use bizarre_logger::{core_error, core_info};
+use specs::{World, WorldExt};
+
+use super::{
+    command::{tokenize, Command, CommandArgs, CommandError},
+    cvar::CVarRegistry,
+};
+
+/// Commands and the lines waiting to run, as a resource. Lines come from
+/// [`Console::submit`], `exec` files and the attached sources, and run at the
+/// end of the frame with exclusive access to the world.
+///
+/// A line starting with the name of a console variable prints it, or sets it
+/// when a value follows.
+pub struct Console {
+    commands: BTreeMap<String, Command>,
+    history: VecDeque<String>,
+    output: VecDeque<String>,
+    pending: VecDeque<String>,
+    sources: Vec<Mutex<Receiver<String>>>,
+    pub max_history: usize,
+    pub max_output: usize,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        let mut console = Self {
+            commands: BTreeMap::new(),
+            history: VecDeque::new(),
+            output: VecDeque::new(),
+            pending: VecDeque::new(),
+            sources: Vec::new(),
+            max_history: 100,
+            max_output: 500,
+        };
+        register_builtins(&mut console);
+        console
+    }
+}
+
+impl Console {
+    /// Replaces the command with the same name
+    pub fn register<F>(&mut self, name: &str, usage: &str, description: &str, handler: F)
+    where
+        F: Fn(&mut World, &CommandArgs) -> anyhow::Result<String> + Send + Sync + 'static,
+    {
+        self.commands.insert(
+            name.into(),
+            Command {
+                usage: usage.into(),
+                description: description.into(),
+                handler: Arc::new(handler),
+            },
+        );
+    }
+
+    pub fn command(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = (&str, &Command)> {
+        self.commands
+            .iter()
+            .map(|(name, command)| (name.as_str(), command))
+    }
+
+    /// Queues the line and adds it to the history
+    pub fn submit(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+
+        if self.history.back().map(String::as_str) != Some(line) {
+            self.history.push_back(line.into());
+            if self.history.len() > self.max_history {
+                self.history.pop_front();
+            }
+        }
+
+        self.pending.push_back(line.into());
+    }
+
+    /// Queues the line without adding it to the history
+    pub fn queue(&mut self, line: &str) {
+        self.pending.push_back(line.into());
+    }
+
+    /// Oldest first
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.history.iter().map(String::as_str)
+    }
+
+    /// Printed lines, oldest first
+    pub fn output(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.output.iter().map(String::as_str)
+    }
+
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            core_info!("{}", line);
+            self.output.push_back(line.into());
+        }
+        while self.output.len() > self.max_output {
+            self.output.pop_front();
+        }
+    }
+
+    /// Lines sent through the channel are submitted at the start of the next
+    /// console run
+    pub fn attach_source(&mut self, source: Receiver<String>) {
+        self.sources.push(Mutex::new(source));
+    }
+
+    /// Submits the lines typed into the terminal
+    pub fn attach_stdin(&mut self) {
+        let (sender, receiver) = channel();
+
+        std::thread::Builder::new()
+            .name("console_stdin".into())
+            .spawn(move || {
+                for line in std::io::stdin().lock().lines() {
+                    let sent = match line {
+                        Ok(line) => sender.send(line),
+                        Err(_) => break,
+                    };
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn the console stdin thread");
+
+        self.attach_source(receiver);
+    }
+
+    /// Full lines the partial `line` can be completed to, sorted. Completes
+    /// the command or variable name, and the variable name after `set`,
+    /// `get` and `reset`.
+    pub fn complete(&self, line: &str, cvars: &CVarRegistry) -> Vec<String> {
+        let cvar_names = || cvars.iter().map(|(name, _)| name);
+
+        let (prefix, partial, candidates): (_, _, Vec<&str>) = match line.rsplit_once(' ') {
+            None => (
+                "",
+                line,
+                self.commands
+                    .keys()
+                    .map(String::as_str)
+                    .chain(cvar_names())
+                    .collect(),
+            ),
+            Some((prefix, partial)) => match prefix.split_whitespace().collect::<Vec<_>>()[..] {
+                ["set" | "get" | "reset"] => (prefix, partial, cvar_names().collect()),
+                ["help"] => (
+                    prefix,
+                    partial,
+                    self.commands.keys().map(String::as_str).collect(),
+                ),
+                _ => return Vec::new(),
+            },
+        };
+
+        let mut completions: Vec<String> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(partial))
+            .map(|candidate| match prefix {
+                "" => candidate.to_string(),
+                prefix => format!("{prefix} {candidate}"),
+            })
+            .collect();
+
+        completions.sort();
+        completions.dedup();
+        completions
+    }
+
+    fn drain_sources(&mut self) {
+        let lines: Vec<String> = self
+            .sources
+            .iter()
+            .flat_map(|source| source.lock().unwrap().try_iter().collect::<Vec<_>>())
+            .collect();
+
+        for line in lines {
+            self.submit(&line);
+        }
+    }
+}
+
+/// Runs a line right away and returns its output
+pub fn execute(world: &mut World, line: &str) -> Result<String, CommandError> {
+    let mut tokens = tokenize(line)?;
+    if tokens.is_empty() {
+        return Ok(String::new());
+    }
+
+    let name = tokens.remove(0);
+    let args = CommandArgs::new(tokens);
+
+    let command = world.read_resource::<Console>().command(&name).cloned();
+    if let Some(command) = command {
+        return (command.handler)(world, &args).map_err(|source| CommandError::Failed {
+            command: name,
+            usage: command.usage,
+            source,
+        });
+    }
+
+    let mut cvars = world.write_resource::<CVarRegistry>();
+    let value = match cvars.get(&name) {
+        Some(value) => value.clone(),
+        None => return Err(CommandError::Unknown(name)),
+    };
+
+    if args.is_empty() {
+        return Ok(format!("{name} = {value}"));
+    }
+
+    cvars
+        .set_from_str(&name, &args.rest(0))
+        .map(|_| String::new())
+        .map_err(|err| CommandError::Failed {
+            command: name,
+            usage: "[value]".into(),
+            source: err.into(),
+        })
+}
+
+/// Runs the lines queued before the call, the ones they queue wait for the
+/// next call so a looping `exec` can't stall the frame
+pub fn run_pending(world: &mut World) {
+    let lines: Vec<String> = {
+        let mut console = world.write_resource::<Console>();
+        console.drain_sources();
+        console.pending.drain(..).collect()
+    };
+
+    for line in lines {
+        let result = execute(world, &line);
+        let mut console = world.write_resource::<Console>();
+
+        match result {
+            Ok(output) => console.print(&output),
+            Err(err) => {
+                core_error!("{}", err);
+                console.output.push_back(err.to_string());
+            }
+        }
+    }
+}
+
+fn register_builtins(console: &mut Console) {
+    console.register(
+        "help",
+        "[command]",
+        "Lists the commands or describes one",
+        |world, args| {
+            let console = world.read_resource::<Console>();
+
+            if let Some(name) = args.get(0) {
+                let command = console
+                    .command(name)
+                    .ok_or_else(|| anyhow!("unknown command '{name}'"))?;
+                return Ok(format!(
+                    "{name} {}\n  {}",
+                    command.usage, command.description
+                ));
+            }
+
+            Ok(console
+                .commands()
+                .map(|(name, command)| {
+                    format!("{name} {} - {}", command.usage, command.description)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        },
+    );
+
+    console.register(
+        "set",
+        "<name> <value>",
+        "Sets a console variable",
+        |world, args| {
+            let name = args.parse::<String>(0)?;
+            if args.len() < 2 {
+                bail!("missing the value");
+            }
+            world
+                .write_resource::<CVarRegistry>()
+                .set_from_str(&name, &args.rest(1))?;
+            Ok(String::new())
+        },
+    );
+
+    console.register(
+        "get",
+        "<name>",
+        "Prints a console variable",
+        |world, args| {
+            let name = args.parse::<String>(0)?;
+            let cvars = world.read_resource::<CVarRegistry>();
+            let var = cvars
+                .var(&name)
+                .ok_or_else(|| anyhow!("unknown console variable '{name}'"))?;
+            Ok(format!(
+                "{name} = {} (default {}) - {}",
+                var.value(),
+                var.default_value(),
+                var.description
+            ))
+        },
+    );
+
+    console.register(
+        "reset",
+        "<name>",
+        "Sets a console variable back to its default",
+        |world, args| {
+            let name = args.parse::<String>(0)?;
+            world.write_resource::<CVarRegistry>().reset(&name)?;
+            Ok(String::new())
+        },
+    );
+
+    console.register(
+        "cvars",
+        "[prefix]",
+        "Lists the console variables",
+        |world, args| {
+            let prefix = args.get(0).unwrap_or_default();
+            Ok(world
+                .read_resource::<CVarRegistry>()
+                .iter()
+                .filter(|(name, _)| name.starts_with(prefix))
+                .map(|(name, var)| format!("{name} = {} - {}", var.value(), var.description))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        },
+    );
+
+    console.register(
+        "exec",
+        "<path>",
+        "Runs the lines of a file, `#` starts a comment",
+        |world, args| {
+            let path = args.rest(0);
+            if path.is_empty() {
+                bail!("missing the path");
+            }
+
+            let source = std::fs::read_to_string(Path::new(&path))
+                .map_err(|err| anyhow!("could not read '{path}': {err}"))?;
+
+            let mut console = world.write_resource::<Console>();
+            for line in source.lines().map(str::trim) {
+                if !line.is_empty() && !line.starts_with('#') {
+                    console.queue(line);
+                }
+            }
+            Ok(String::new())
+        },
+    );
+
+    console.register("echo", "<text>", "Prints the text", |_, args| {
+        Ok(args.rest(0))
+    });
+
+    console.register("history", "", "Prints the submitted lines", |world, _| {
+        Ok(world
+            .read_resource::<Console>()
+            .history()
+            .collect::<Vec<_>>()
+            .join("\n"))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        let mut cvars = CVarRegistry::default();
+        cvars.register("r.msaa", 2, "MSAA samples");
+        cvars.register("r.wireframe", false, "Draws the meshes as lines");
+        world.insert(cvars);
+        world.insert(Console::default());
+        world
+    }
+
+    #[test]
+    fn runs_commands_and_cvar_lines() {
+        let mut world = world();
+
+        {
+            let mut console = world.write_resource::<Console>();
+            console.submit("set r.msaa 4");
+            console.submit("r.wireframe on");
+            console.submit("echo \"hello  there\"");
+            console.submit("nope");
+        }
+        run_pending(&mut world);
+
+        let cvars = world.read_resource::<CVarRegistry>();
+        assert_eq!(cvars.get_int("r.msaa"), Some(4));
+        assert_eq!(cvars.get_bool("r.wireframe"), Some(true));
+        drop(cvars);
+
+        let console = world.read_resource::<Console>();
+        assert!(console.output().any(|line| line == "hello  there"));
+        assert!(console.output().last().unwrap().contains("nope"));
+        assert_eq!(console.history().count(), 4);
+        drop(console);
+
+        assert_eq!(execute(&mut world, "r.msaa").unwrap(), "r.msaa = 4");
+        assert!(matches!(
+            execute(&mut world, "set r.msaa"),
+            Err(CommandError::Failed { .. })
+        ));
+    }
+
+    #[test]
+    fn completes_names() {
+        let world = world();
+        let console = world.read_resource::<Console>();
+        let cvars = world.read_resource::<CVarRegistry>();
+
+        assert_eq!(
+            console.complete("r.", &cvars),
+            vec!["r.msaa", "r.wireframe"]
+        );
+        assert_eq!(console.complete("set r.w", &cvars), vec!["set r.wireframe"]);
+        assert_eq!(console.complete("he", &cvars), vec!["help"]);
+        assert!(console.complete("echo r.", &cvars).is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+use thiserror::Error;
+
+use crate::engine_config::EngineConfig;
+
+#[derive(Debug, Error)]
+pub enum CVarError {
+    #[error("unknown console variable '{0}'")]
+    Unknown(String),
+
+    #[error("'{value}' is not a valid {expected} for '{name}'")]
+    Parse {
+        name: String,
+        value: String,
+        expected: &'static str,
+    },
+
+    #[error("'{name}' is a {expected}, not a {found}")]
+    WrongType {
+        name: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("'{name}' does not accept '{value}': {reason}")]
+    Rejected {
+        name: String,
+        value: String,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CVarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl CVarValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            CVarValue::Bool(_) => "bool",
+            CVarValue::Int(_) => "int",
+            CVarValue::Float(_) => "float",
+            CVarValue::String(_) => "string",
+        }
+    }
+
+    /// Parses `value` as the type of `self`. Booleans also accept `1`, `0`,
+    /// `on` and `off`.
+    pub fn parse_same(&self, value: &str) -> Option<CVarValue> {
+        match self {
+            CVarValue::Bool(_) => match value.to_ascii_lowercase().as_str() {
+                "true" | "1" | "on" => Some(CVarValue::Bool(true)),
+                "false" | "0" | "off" => Some(CVarValue::Bool(false)),
+                _ => None,
+            },
+            CVarValue::Int(_) => value.parse().ok().map(CVarValue::Int),
+            CVarValue::Float(_) => value.parse().ok().map(CVarValue::Float),
+            CVarValue::String(_) => Some(CVarValue::String(value.into())),
+        }
+    }
+
+    /// Same type as `self`, ints are accepted for floats
+    fn coerce(&self, value: CVarValue) -> Option<CVarValue> {
+        match (self, value) {
+            (CVarValue::Float(_), CVarValue::Int(value)) => Some(CVarValue::Float(value as f64)),
+            (current, value) if current.type_name() == value.type_name() => Some(value),
+            _ => None,
+        }
+    }
+
+    fn from_config(value: &toml::Value) -> Option<CVarValue> {
+        match value {
+            toml::Value::Boolean(value) => Some(CVarValue::Bool(*value)),
+            toml::Value::Integer(value) => Some(CVarValue::Int(*value)),
+            toml::Value::Float(value) => Some(CVarValue::Float(*value)),
+            toml::Value::String(value) => Some(CVarValue::String(value.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl Display for CVarValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CVarValue::Bool(value) => write!(f, "{}", value),
+            CVarValue::Int(value) => write!(f, "{}", value),
+            CVarValue::Float(value) => write!(f, "{}", value),
+            CVarValue::String(value) => write!(f, "\"{}\"", value),
+        }
+    }
+}
+
+impl From<bool> for CVarValue {
+    fn from(value: bool) -> Self {
+        CVarValue::Bool(value)
+    }
+}
+
+impl From<i32> for CVarValue {
+    fn from(value: i32) -> Self {
+        CVarValue::Int(value as i64)
+    }
+}
+
+impl From<i64> for CVarValue {
+    fn from(value: i64) -> Self {
+        CVarValue::Int(value)
+    }
+}
+
+impl From<f64> for CVarValue {
+    fn from(value: f64) -> Self {
+        CVarValue::Float(value)
+    }
+}
+
+impl From<&str> for CVarValue {
+    fn from(value: &str) -> Self {
+        CVarValue::String(value.into())
+    }
+}
+
+impl From<String> for CVarValue {
+    fn from(value: String) -> Self {
+        CVarValue::String(value)
+    }
+}
+
+/// Called with the new value before it is stored, an error rejects it
+pub type CVarCallback = dyn Fn(&CVarValue) -> Result<(), String> + Send + Sync;
+
+pub struct CVar {
+    pub description: String,
+    value: CVarValue,
+    default: CVarValue,
+    callbacks: Vec<Box<CVarCallback>>,
+}
+
+impl CVar {
+    pub fn value(&self) -> &CVarValue {
+        &self.value
+    }
+
+    pub fn default_value(&self) -> &CVarValue {
+        &self.default
+    }
+}
+
+/// Typed variables tweaked at runtime through the [`super::Console`], named
+/// with dots like `log.level`. The engine config sets the ones it has a key
+/// for when the app is built.
+#[derive(Default)]
+pub struct CVarRegistry {
+    vars: BTreeMap<String, CVar>,
+}
+
+impl CVarRegistry {
+    /// Registering a variable again only updates the description, the value
+    /// and the callbacks are kept
+    pub fn register<V: Into<CVarValue>>(&mut self, name: &str, default: V, description: &str) {
+        let default = default.into();
+
+        self.vars
+            .entry(name.into())
+            .and_modify(|var| var.description = description.into())
+            .or_insert_with(|| CVar {
+                description: description.into(),
+                value: default.clone(),
+                default,
+                callbacks: Vec::new(),
+            });
+    }
+
+    /// Runs `callback` on every change of the variable. It isn't called for
+    /// the current value.
+    pub fn on_change<F>(&mut self, name: &str, callback: F) -> Result<(), CVarError>
+    where
+        F: Fn(&CVarValue) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.var_mut(name)?.callbacks.push(Box::new(callback));
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.vars.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CVarValue> {
+        self.vars.get(name).map(|var| &var.value)
+    }
+
+    pub fn var(&self, name: &str) -> Option<&CVar> {
+        self.vars.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &CVar)> {
+        self.vars.iter().map(|(name, var)| (name.as_str(), var))
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            CVarValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            CVarValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            CVarValue::Float(value) => Some(*value),
+            CVarValue::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            CVarValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn set<V: Into<CVarValue>>(&mut self, name: &str, value: V) -> Result<(), CVarError> {
+        let value = value.into();
+        let var = self.var_mut(name)?;
+
+        let value = match var.value.coerce(value.clone()) {
+            Some(value) => value,
+            None => {
+                return Err(CVarError::WrongType {
+                    name: name.into(),
+                    expected: var.value.type_name(),
+                    found: value.type_name(),
+                })
+            }
+        };
+
+        for callback in &var.callbacks {
+            callback(&value).map_err(|reason| CVarError::Rejected {
+                name: name.into(),
+                value: value.to_string(),
+                reason,
+            })?;
+        }
+
+        var.value = value;
+        Ok(())
+    }
+
+    /// Parses the value as the type of the variable
+    pub fn set_from_str(&mut self, name: &str, value: &str) -> Result<(), CVarError> {
+        let parsed = self.var_mut(name)?.value.parse_same(value);
+
+        match parsed {
+            Some(parsed) => self.set(name, parsed),
+            None => Err(CVarError::Parse {
+                name: name.into(),
+                value: value.into(),
+                expected: self.vars[name].value.type_name(),
+            }),
+        }
+    }
+
+    pub fn reset(&mut self, name: &str) -> Result<(), CVarError> {
+        let default = self.var_mut(name)?.default.clone();
+        self.set(name, default)
+    }
+
+    /// Sets the variables the config has a key for, the errors are returned
+    /// after every variable was tried
+    pub fn apply_config(&mut self, config: &EngineConfig) -> Vec<CVarError> {
+        let names: Vec<String> = self.vars.keys().cloned().collect();
+        let mut errors = Vec::new();
+
+        for name in names {
+            let value = match config.value(&name) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let result = match (value, CVarValue::from_config(value)) {
+                (toml::Value::String(text), _) => self.set_from_str(&name, text),
+                (_, Some(value)) => self.set(&name, value),
+                (value, None) => Err(CVarError::Parse {
+                    name: name.clone(),
+                    value: value.to_string(),
+                    expected: self.vars[&name].value.type_name(),
+                }),
+            };
+
+            if let Err(err) = result {
+                errors.push(err);
+            }
+        }
+
+        errors
+    }
+
+    fn var_mut(&mut self, name: &str) -> Result<&mut CVar, CVarError> {
+        self.vars
+            .get_mut(name)
+            .ok_or_else(|| CVarError::Unknown(name.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn sets_typed_values_and_runs_callbacks() {
+        let mut cvars = CVarRegistry::default();
+        cvars.register("r.msaa", 2, "MSAA samples");
+        cvars.register("r.wireframe", false, "Draws the meshes as lines");
+        cvars.register("r.gamma", 2.2, "Display gamma");
+
+        let seen = Arc::new(AtomicI64::new(0));
+        let seen_by_callback = seen.clone();
+        cvars
+            .on_change("r.msaa", move |value| match value {
+                CVarValue::Int(samples) if *samples > 0 => {
+                    seen_by_callback.store(*samples, Ordering::Relaxed);
+                    Ok(())
+                }
+                _ => Err("expected a positive sample count".into()),
+            })
+            .unwrap();
+
+        cvars.set_from_str("r.msaa", "4").unwrap();
+        cvars.set_from_str("r.wireframe", "on").unwrap();
+        cvars.set("r.gamma", 2).unwrap();
+        assert_eq!(seen.load(Ordering::Relaxed), 4);
+        assert_eq!(cvars.get_bool("r.wireframe"), Some(true));
+        assert_eq!(cvars.get_float("r.gamma"), Some(2.0));
+
+        assert!(cvars.set("r.msaa", 0).is_err());
+        assert!(cvars.set("r.msaa", "eight").is_err());
+        assert!(cvars.set_from_str("r.wireframe", "maybe").is_err());
+        assert_eq!(cvars.get_int("r.msaa"), Some(4));
+
+        cvars.reset("r.msaa").unwrap();
+        assert_eq!(cvars.get_int("r.msaa"), Some(2));
+    }
+
+    #[test]
+    fn takes_values_from_the_config() {
+        let mut cvars = CVarRegistry::default();
+        cvars.register("log.level", "Info", "");
+        cvars.register("r.msaa", 2, "");
+
+        let config = EngineConfig::from_toml_str("log.level = \"Debug\"\nr.msaa = true").unwrap();
+        let errors = cvars.apply_config(&config);
+
+        assert_eq!(cvars.get_str("log.level"), Some("Debug"));
+        assert_eq!(errors.len(), 1);
+    }
+}
@@ -1,3 +1,8 @@
+#[derive(Debug, Clone)]
+pub struct WindowFocusChanged {
+    pub focused: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct WindowResized {
     pub width: f32,
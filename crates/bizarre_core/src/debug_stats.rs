@@ -1,3 +1,10 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use crate::profiler::SystemTimeStats;
+
 #[derive(Debug, Default)]
 pub struct DebugStats {
     /// Last frame work time in milliseconds
@@ -6,4 +13,93 @@ pub struct DebugStats {
     pub last_frame_idle_time_ms: f64,
     /// Last frame total time in milliseconds
     pub last_frame_total_time_ms: f64,
+    /// Frames per second over the last [`DebugStats::FRAME_HISTORY_LEN`] frames
+    pub rolling_fps: f64,
+    /// Median frame time in milliseconds
+    pub frame_time_p50_ms: f64,
+    /// 95th percentile frame time in milliseconds
+    pub frame_time_p95_ms: f64,
+    /// 99th percentile frame time in milliseconds
+    pub frame_time_p99_ms: f64,
+    /// Run times of the profiled systems by name, empty unless profiling is
+    /// enabled
+    pub system_times: BTreeMap<String, SystemTimeStats>,
+    frame_history: VecDeque<f64>,
+}
+
+impl DebugStats {
+    pub const FRAME_HISTORY_LEN: usize = 240;
+
+    pub fn record_frame(&mut self, work: Duration, idle: Duration, total: Duration) {
+        self.last_frame_work_time_ms = work.as_secs_f64() * 1000.0;
+        self.last_frame_idle_time_ms = idle.as_secs_f64() * 1000.0;
+        self.last_frame_total_time_ms = total.as_secs_f64() * 1000.0;
+
+        if self.frame_history.len() == Self::FRAME_HISTORY_LEN {
+            self.frame_history.pop_front();
+        }
+        self.frame_history.push_back(self.last_frame_total_time_ms);
+
+        let total_ms: f64 = self.frame_history.iter().sum();
+        self.rolling_fps = if total_ms > 0.0 {
+            self.frame_history.len() as f64 * 1000.0 / total_ms
+        } else {
+            0.0
+        };
+
+        self.frame_time_p50_ms = self.frame_time_percentile(50.0);
+        self.frame_time_p95_ms = self.frame_time_percentile(95.0);
+        self.frame_time_p99_ms = self.frame_time_percentile(99.0);
+    }
+
+    /// Frame time in milliseconds below which `percentile` percent of the
+    /// recent frames fall (nearest-rank)
+    pub fn frame_time_percentile(&self, percentile: f64) -> f64 {
+        if self.frame_history.is_empty() {
+            return 0.0;
+        }
+
+        let mut sorted = self.frame_history.iter().copied().collect::<Vec<_>>();
+        sorted.sort_by(f64::total_cmp);
+
+        let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::DebugStats;
+
+    #[test]
+    fn computes_rolling_fps_and_percentiles() {
+        let mut stats = DebugStats::default();
+
+        for ms in 1..=100 {
+            let total = Duration::from_millis(ms);
+            stats.record_frame(total, Duration::ZERO, total);
+        }
+
+        assert_eq!(stats.frame_time_p50_ms, 50.0);
+        assert_eq!(stats.frame_time_p95_ms, 95.0);
+        assert_eq!(stats.frame_time_p99_ms, 99.0);
+        assert!((stats.rolling_fps - 100.0 * 1000.0 / 5050.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn keeps_a_bounded_history() {
+        let mut stats = DebugStats::default();
+
+        for _ in 0..DebugStats::FRAME_HISTORY_LEN {
+            stats.record_frame(Duration::ZERO, Duration::ZERO, Duration::from_millis(100));
+        }
+        for _ in 0..DebugStats::FRAME_HISTORY_LEN {
+            stats.record_frame(Duration::ZERO, Duration::ZERO, Duration::from_millis(10));
+        }
+
+        assert!((stats.rolling_fps - 100.0).abs() < 1e-9);
+        assert_eq!(stats.frame_time_p99_ms, 10.0);
+    }
 }
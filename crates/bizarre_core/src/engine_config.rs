@@ -0,0 +1,255 @@
+use std::path::Path;
+
+use bizarre_logger::core_warn;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use toml::{Table, Value};
+
+#[derive(Debug, Error)]
+pub enum EngineConfigError {
+    #[error("could not access the engine config '{path}': {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("could not parse the engine config '{path}': {source}")]
+    Parse { path: String, source: anyhow::Error },
+
+    #[error("unsupported engine config format '{0}', expected .toml or .ron")]
+    UnsupportedFormat(String),
+
+    #[error("invalid override '{0}', expected key=value")]
+    InvalidOverride(String),
+
+    #[error("'{key}' is not a valid {expected}: {source}")]
+    WrongType {
+        key: String,
+        expected: &'static str,
+        source: Box<toml::de::Error>,
+    },
+}
+
+/// Engine settings addressed by dotted keys, e.g. `window.width`. Loaded by
+/// the [`crate::app_builder::AppBuilder`] and available to the layers while
+/// they are attached, then as a resource.
+///
+/// ```toml
+/// [window]
+/// width = 1280
+/// title = "Sandbox"
+///
+/// [render]
+/// msaa_samples = 4
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EngineConfig {
+    values: Table,
+}
+
+impl EngineConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, EngineConfigError> {
+        let path = path.as_ref();
+        let path_str = path.to_string_lossy().to_string();
+
+        let source = std::fs::read_to_string(path).map_err(|source| EngineConfigError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&source),
+            Some("ron") => Self::from_ron_str(&source),
+            _ => return Err(EngineConfigError::UnsupportedFormat(path_str)),
+        };
+
+        parsed.map_err(|source| EngineConfigError::Parse {
+            path: path_str,
+            source,
+        })
+    }
+
+    pub fn from_toml_str(source: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            values: toml::from_str(source)?,
+        })
+    }
+
+    pub fn from_ron_str(source: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            values: ron::from_str(source)?,
+        })
+    }
+
+    pub fn value(&self, key: &str) -> Option<&Value> {
+        let mut parts = key.split('.');
+        let mut value = self.values.get(parts.next()?)?;
+
+        for part in parts {
+            value = value.as_table()?.get(part)?;
+        }
+
+        Some(value)
+    }
+
+    /// `Ok(None)` when the key isn't set
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, EngineConfigError> {
+        match self.value(key) {
+            Some(value) => {
+                value
+                    .clone()
+                    .try_into()
+                    .map(Some)
+                    .map_err(|source| EngineConfigError::WrongType {
+                        key: key.into(),
+                        expected: std::any::type_name::<T>(),
+                        source: Box::new(source),
+                    })
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Falls back to `default` when the key isn't set or has the wrong type
+    pub fn get_or<T: DeserializeOwned>(&self, key: &str, default: T) -> T {
+        match self.get(key) {
+            Ok(Some(value)) => value,
+            Ok(None) => default,
+            Err(err) => {
+                core_warn!("Using the default of '{}': {}", key, err);
+                default
+            }
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.value(key).is_some()
+    }
+
+    /// Sets the value, replacing whatever was on the way of the key
+    pub fn set<V: Into<Value>>(&mut self, key: &str, value: V) {
+        let mut parts = key.split('.').peekable();
+        let mut table = &mut self.values;
+
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                table.insert(part.into(), value.into());
+                return;
+            }
+
+            let entry = table
+                .entry(part)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if !entry.is_table() {
+                *entry = Value::Table(Table::new());
+            }
+
+            table = entry.as_table_mut().expect("Replaced with a table above");
+        }
+    }
+
+    /// Sets the value only if the key isn't set yet
+    pub fn set_default<V: Into<Value>>(&mut self, key: &str, value: V) {
+        if !self.contains(key) {
+            self.set(key, value);
+        }
+    }
+
+    /// Applies a `key=value` override. The value is read as TOML, and as a
+    /// plain string when that fails, so `window.title=My game` works.
+    pub fn apply_override(&mut self, assignment: &str) -> Result<(), EngineConfigError> {
+        let (key, value) = match assignment.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => (key.trim(), value.trim()),
+            _ => return Err(EngineConfigError::InvalidOverride(assignment.into())),
+        };
+
+        let value = match toml::from_str::<Table>(&format!("value = {value}")) {
+            Ok(mut table) => table.remove("value").unwrap_or_else(|| value.into()),
+            Err(_) => Value::String(value.into()),
+        };
+
+        self.set(key, value);
+        Ok(())
+    }
+
+    /// Applies every `--set key=value` and `--set=key=value` of the command
+    /// line, other arguments are skipped
+    pub fn apply_args<I, S>(&mut self, args: I) -> Result<(), EngineConfigError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let arg = arg.as_ref();
+
+            if let Some(assignment) = arg.strip_prefix("--set=") {
+                self.apply_override(assignment)?;
+            } else if arg == "--set" {
+                match args.next() {
+                    Some(assignment) => self.apply_override(assignment.as_ref())?,
+                    None => return Err(EngineConfigError::InvalidOverride(arg.into())),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Values of `other` take precedence
+    pub fn merge(&mut self, other: EngineConfig) {
+        fn merge_tables(into: &mut Table, from: Table) {
+            for (key, value) in from {
+                match (into.get_mut(&key), value) {
+                    (Some(Value::Table(into)), Value::Table(from)) => merge_tables(into, from),
+                    (_, value) => {
+                        into.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        merge_tables(&mut self.values, other.values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_dotted_keys_and_overrides() {
+        let mut config = EngineConfig::from_toml_str(
+            r#"
+            [window]
+            width = 1280
+            title = "Sandbox"
+            "#,
+        )
+        .unwrap();
+
+        config
+            .apply_args([
+                "sandbox",
+                "--set",
+                "window.title=My game",
+                "--set=render.msaa_samples=4",
+            ])
+            .unwrap();
+
+        assert_eq!(config.get_or("window.width", 800u32), 1280);
+        assert_eq!(config.get_or("window.height", 600u32), 600);
+        assert_eq!(config.get_or("window.title", String::new()), "My game");
+        assert_eq!(config.get::<u32>("render.msaa_samples").unwrap(), Some(4));
+        assert!(config.get::<bool>("window.width").is_err());
+        assert!(config.apply_override("novalue").is_err());
+    }
+
+    #[test]
+    fn ron_matches_toml() {
+        let ron = EngineConfig::from_ron_str(r#"{ "window": { "width": 1280 } }"#).unwrap();
+        let toml = EngineConfig::from_toml_str("window.width = 1280").unwrap();
+        assert_eq!(ron, toml);
+    }
+}
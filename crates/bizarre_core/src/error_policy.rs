@@ -0,0 +1,207 @@
+use std::{collections::HashMap, time::Duration};
+
+use bizarre_common::system_error::SystemError;
+use bizarre_logger::{core_critical, core_error, core_warn, rate_limit::RateLimiter};
+
+/// Failures of a system after the first one in a row are logged at most
+/// this often
+pub const REPEATED_FAILURE_LOG_PERIOD: Duration = Duration::from_secs(5);
+
+/// What the app does when a fallible system returns an error
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Logs the error, the system keeps running. Repeated failures are
+    /// logged once per [`REPEATED_FAILURE_LOG_PERIOD`].
+    #[default]
+    Log,
+    /// Keeps running the system until it fails `max_attempts` frames in a
+    /// row, then disables it
+    Retry { max_attempts: u32 },
+    /// Stops the app on the first error
+    StopApp,
+    /// Logs the errors like [`ErrorPolicy::Log`], and stops the app once the
+    /// system fails `repeats` frames in a row
+    Escalate { repeats: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorAction {
+    Continue,
+    DisableSystem,
+    StopApp,
+}
+
+/// Default policy and per-system overrides set on the
+/// [`crate::app_builder::AppBuilder`]
+#[derive(Debug, Clone, Default)]
+pub struct ErrorPolicies {
+    pub default: ErrorPolicy,
+    pub systems: HashMap<String, ErrorPolicy>,
+}
+
+impl ErrorPolicies {
+    pub fn policy_for(&self, system: &str) -> ErrorPolicy {
+        self.systems.get(system).copied().unwrap_or(self.default)
+    }
+
+    pub(crate) fn handle(&self, error: &SystemError, log: &mut FailureLog) -> ErrorAction {
+        let SystemError {
+            system,
+            error,
+            consecutive_failures: failures,
+        } = error;
+
+        match self.policy_for(system) {
+            ErrorPolicy::Log => {
+                log.failed(system, *failures, error);
+                ErrorAction::Continue
+            }
+            ErrorPolicy::Retry { max_attempts } if *failures >= max_attempts => {
+                core_error!(
+                    "System \"{}\" failed {} times in a row, disabling it: {:?}",
+                    system,
+                    failures,
+                    error
+                );
+                ErrorAction::DisableSystem
+            }
+            ErrorPolicy::Retry { max_attempts } => {
+                core_warn!(
+                    "System \"{}\" failed (attempt {} of {}): {:?}",
+                    system,
+                    failures,
+                    max_attempts,
+                    error
+                );
+                ErrorAction::Continue
+            }
+            ErrorPolicy::StopApp => {
+                core_critical!(
+                    "System \"{}\" failed, stopping the app: {:?}",
+                    system,
+                    error
+                );
+                ErrorAction::StopApp
+            }
+            ErrorPolicy::Escalate { repeats } if *failures >= repeats => {
+                core_critical!(
+                    "System \"{}\" failed {} times in a row, stopping the app: {:?}",
+                    system,
+                    failures,
+                    error
+                );
+                ErrorAction::StopApp
+            }
+            ErrorPolicy::Escalate { .. } => {
+                log.failed(system, *failures, error);
+                ErrorAction::Continue
+            }
+        }
+    }
+}
+
+/// Rate limits the failure logs of each system
+#[derive(Default)]
+pub(crate) struct FailureLog {
+    limiters: HashMap<String, RateLimiter>,
+}
+
+impl FailureLog {
+    /// The first failure in a row is always logged
+    fn failed(&mut self, system: &str, failures: u32, error: &anyhow::Error) {
+        if failures <= 1 {
+            self.limiters.insert(system.into(), RateLimiter::new());
+        }
+
+        let limiter = self.limiters.entry(system.into()).or_default();
+        if !limiter.should_log(REPEATED_FAILURE_LOG_PERIOD) {
+            return;
+        }
+
+        if failures <= 1 {
+            core_error!("System \"{}\" failed: {:?}", system, error);
+        } else {
+            core_error!(
+                "System \"{}\" failed {} times in a row: {:?}",
+                system,
+                failures,
+                error
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+    use bizarre_common::system_error::{DisabledSystems, TrySystem};
+    use bizarre_logger::{log_capture::capture_logs, LogLevel};
+    use specs::{WorldExt, Write};
+
+    use super::*;
+    use crate::{
+        frame_pacing::FramePacing,
+        schedule::{ScheduleType, SystemConfig},
+        App,
+    };
+
+    #[derive(Default)]
+    struct Runs(u32);
+
+    struct Failing;
+
+    impl<'a> TrySystem<'a> for Failing {
+        type SystemData = Write<'a, Runs>;
+
+        fn try_run(&mut self, mut runs: Self::SystemData) -> anyhow::Result<()> {
+            runs.0 += 1;
+            bail!("run {} failed", runs.0)
+        }
+    }
+
+    fn build_app(policy: ErrorPolicy) -> App {
+        App::builder()
+            .name("Error policy test")
+            .isolated()
+            .with_frame_pacing(FramePacing::Uncapped)
+            .with_system_error_policy("failing", policy)
+            .with_try_system(ScheduleType::Frame, Failing, SystemConfig::new("failing"))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn retry_disables_the_system() {
+        let mut app = build_app(ErrorPolicy::Retry { max_attempts: 2 });
+
+        assert_eq!(app.run_frames(5), 5);
+        assert_eq!(app.world().read_resource::<Runs>().0, 2);
+        assert!(app
+            .world()
+            .read_resource::<DisabledSystems>()
+            .is_disabled("failing"));
+    }
+
+    #[test]
+    fn repeated_failures_are_rate_limited() {
+        let mut app = build_app(ErrorPolicy::Log);
+
+        let logs = capture_logs(|| {
+            app.run_frames(5);
+        });
+
+        assert_eq!(app.world().read_resource::<Runs>().0, 5);
+        assert_eq!(logs.with_level(LogLevel::Error).count(), 1);
+        assert!(logs.contains(LogLevel::Error, "run 1 failed"));
+    }
+
+    #[test]
+    fn escalate_stops_the_app() {
+        let mut app = build_app(ErrorPolicy::Escalate { repeats: 3 });
+        assert_eq!(app.run_frames(10), 3);
+
+        let mut app = build_app(ErrorPolicy::StopApp);
+        assert_eq!(app.run_frames(10), 1);
+        assert!(!app.is_running());
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use bizarre_logger::core_warn;
+
+/// Accumulator driving the [`crate::schedule::ScheduleType::FixedUpdate`]
+/// dispatcher
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: Duration,
+    max_substeps: u32,
+    accumulator: Duration,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_STEP, Self::DEFAULT_MAX_SUBSTEPS)
+    }
+}
+
+impl FixedTimestep {
+    /// 60 updates per second
+    pub const DEFAULT_STEP: Duration = Duration::from_nanos(16_666_667);
+    pub const DEFAULT_MAX_SUBSTEPS: u32 = 5;
+    /// Smaller steps, zero included, are raised to it
+    pub const MIN_STEP: Duration = Duration::from_millis(1);
+
+    pub fn new(step: Duration, max_substeps: u32) -> Self {
+        Self {
+            step: Self::clamp_step(step),
+            max_substeps,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn set_step(&mut self, step: Duration) {
+        self.step = Self::clamp_step(step);
+    }
+
+    fn clamp_step(step: Duration) -> Duration {
+        if step < Self::MIN_STEP {
+            core_warn!(
+                "Fixed timestep of {:?} is too small, using {:?}",
+                step,
+                Self::MIN_STEP
+            );
+            Self::MIN_STEP
+        } else {
+            step
+        }
+    }
+
+    pub fn max_substeps(&self) -> u32 {
+        self.max_substeps
+    }
+
+    pub fn set_max_substeps(&mut self, max_substeps: u32) {
+        self.max_substeps = max_substeps;
+    }
+
+    /// Accumulates the frame time and returns how many fixed steps should be
+    /// run this frame. Time that doesn't fit into `max_substeps` is dropped
+    /// so a slow frame can't make the next ones even slower.
+    pub fn advance(&mut self, delta: Duration) -> u32 {
+        self.accumulator += delta;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_substeps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        if self.accumulator >= self.step {
+            let remainder = self.accumulator.as_nanos() % self.step.as_nanos();
+            self.accumulator = Duration::from_nanos(remainder as u64);
+        }
+
+        steps
+    }
+
+    /// How far the accumulator is into the next step, in `[0, 1)`. Use it to
+    /// interpolate between the last two fixed states when rendering.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.step.as_secs_f64()) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::FixedTimestep;
+
+    #[test]
+    fn accumulates_partial_frames() {
+        let mut timestep = FixedTimestep::new(Duration::from_millis(10), 5);
+
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 0);
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 0);
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn runs_several_steps_for_long_frames() {
+        let mut timestep = FixedTimestep::new(Duration::from_millis(10), 5);
+
+        assert_eq!(timestep.advance(Duration::from_millis(35)), 3);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn clamps_substeps_and_drops_the_backlog() {
+        let mut timestep = FixedTimestep::new(Duration::from_millis(10), 2);
+
+        assert_eq!(timestep.advance(Duration::from_millis(1005)), 2);
+        assert!(timestep.alpha() < 1.0);
+        assert_eq!(timestep.advance(Duration::ZERO), 0);
+    }
+
+    #[test]
+    fn zero_step_is_clamped() {
+        let mut timestep = FixedTimestep::new(Duration::ZERO, 5);
+        assert_eq!(timestep.step(), FixedTimestep::MIN_STEP);
+
+        timestep.set_step(Duration::ZERO);
+        assert_eq!(timestep.advance(Duration::from_millis(3)), 3);
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+/// How long the app waits between frames. Inserted into the world as a
+/// resource, so it can be changed while the app is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramePacing {
+    /// Start the next frame as soon as the current one is done
+    Uncapped,
+    TargetFps(u32),
+    /// Runs at `target_fps` while the window is focused and drops to
+    /// `background_fps` when it isn't
+    Adaptive {
+        target_fps: u32,
+        background_fps: u32,
+    },
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        Self::TargetFps(60)
+    }
+}
+
+impl FramePacing {
+    pub fn frame_budget(&self, focused: bool) -> Option<Duration> {
+        let fps = match *self {
+            FramePacing::Uncapped => return None,
+            FramePacing::TargetFps(fps) => fps,
+            FramePacing::Adaptive {
+                target_fps,
+                background_fps,
+            } => {
+                if focused {
+                    target_fps
+                } else {
+                    background_fps
+                }
+            }
+        };
+
+        if fps == 0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(1.0 / fps as f64))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    /// The last part of the wait is spent spinning, OS sleep is too coarse
+    /// for it
+    pub spin_threshold: Duration,
+    pub focused: bool,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self {
+            spin_threshold: Duration::from_millis(1),
+            focused: true,
+        }
+    }
+}
+
+impl FramePacer {
+    /// Waits until the frame budget is used up and returns the time spent
+    /// waiting
+    pub fn wait(&self, pacing: &FramePacing, frame_start: Instant) -> Duration {
+        let wait_start = Instant::now();
+
+        if let Some(budget) = pacing.frame_budget(self.focused) {
+            sleep_until(frame_start + budget, self.spin_threshold);
+        }
+
+        wait_start.elapsed()
+    }
+}
+
+/// Sleeps until `spin_threshold` before the deadline, then spins
+pub fn sleep_until(deadline: Instant, spin_threshold: Duration) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+
+        let remaining = deadline - now;
+        if remaining > spin_threshold {
+            std::thread::sleep(remaining - spin_threshold);
+        } else {
+            std::hint::spin_loop();
+        }
+    }
+}
@@ -1,9 +1,19 @@
+pub mod action_map;
+pub mod action_state;
+pub mod cursor_mode;
+pub mod gamepad;
 pub mod input_event;
 pub mod input_handler;
+pub mod input_recording;
 pub mod key_codes;
 pub mod mouse_button;
 
+pub use action_map::*;
+pub use action_state::*;
+pub use cursor_mode::*;
+pub use gamepad::*;
 pub use input_event::*;
 pub use input_handler::*;
+pub use input_recording::*;
 pub use key_codes::*;
 pub use mouse_button::*;
@@ -0,0 +1,309 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    gamepad::{GamepadAxis, GamepadButton},
+    input_handler::InputHandler,
+    key_codes::KeyboardKey,
+    mouse_button::MouseButton,
+    KeyboardModifiers, ModifierMatch,
+};
+
+#[derive(Debug, Error)]
+pub enum ActionMapError {
+    #[error("could not access the action map '{path}': {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("could not parse the action map '{path}': {source}")]
+    Parse { path: String, source: anyhow::Error },
+
+    #[error("could not serialize the action map: {0}")]
+    Serialize(anyhow::Error),
+
+    #[error("unsupported action map format '{0}', expected .toml or .ron")]
+    UnsupportedFormat(String),
+}
+
+/// Input that triggers a digital action. The modifiers have to be held
+/// along with it, either side counts, and other modifiers don't matter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionBinding {
+    Key {
+        key: KeyboardKey,
+        #[serde(default, skip_serializing_if = "KeyboardModifiers::is_empty")]
+        modifiers: KeyboardModifiers,
+    },
+    Mouse {
+        button: MouseButton,
+        #[serde(default, skip_serializing_if = "KeyboardModifiers::is_empty")]
+        modifiers: KeyboardModifiers,
+    },
+    /// The button on any connected gamepad
+    Gamepad { button: GamepadButton },
+}
+
+impl ActionBinding {
+    pub fn key(key: KeyboardKey) -> Self {
+        Self::Key {
+            key,
+            modifiers: KeyboardModifiers::NONE,
+        }
+    }
+
+    pub fn mouse(button: MouseButton) -> Self {
+        Self::Mouse {
+            button,
+            modifiers: KeyboardModifiers::NONE,
+        }
+    }
+
+    pub fn is_pressed(&self, input: &InputHandler) -> bool {
+        match self {
+            Self::Key { key, modifiers } => {
+                input.is_key_pressed_matching(key, modifiers, ModifierMatch::Subset)
+            }
+            Self::Mouse { button, modifiers } => {
+                input.is_button_pressed_matching(button, modifiers, ModifierMatch::Subset)
+            }
+            Self::Gamepad { button } => input.gamepads().any(|(_, g)| g.is_pressed(*button)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseAxis {
+    X,
+    Y,
+}
+
+/// Input that drives an analog axis
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    /// -1 while `negative` is held, 1 while `positive` is
+    Keys {
+        negative: KeyboardKey,
+        positive: KeyboardKey,
+    },
+    /// Cursor movement of this frame in pixels, times `scale`
+    MouseMotion { axis: MouseAxis, scale: f32 },
+    /// Device movement of this frame, times `scale`. Keeps coming when the
+    /// cursor is locked or at the window edge.
+    RawMouseMotion { axis: MouseAxis, scale: f32 },
+    /// Wheel movement of this frame, times `scale`
+    MouseWheel { axis: MouseAxis, scale: f32 },
+    /// The axis of the connected gamepad furthest from rest, after the dead
+    /// zone, times `scale`
+    Gamepad { axis: GamepadAxis, scale: f32 },
+}
+
+impl AxisBinding {
+    pub fn value(&self, input: &InputHandler) -> f32 {
+        let pick = |axis: &MouseAxis, [x, y]: [f32; 2]| match axis {
+            MouseAxis::X => x,
+            MouseAxis::Y => y,
+        };
+
+        match self {
+            Self::Keys { negative, positive } => {
+                let negative = input.is_key_down(negative) as i32 as f32;
+                let positive = input.is_key_down(positive) as i32 as f32;
+                positive - negative
+            }
+            Self::MouseMotion { axis, scale } => {
+                let delta = input.mouse_delta();
+                pick(axis, [delta.x, delta.y]) * scale
+            }
+            Self::RawMouseMotion { axis, scale } => {
+                let delta = input.raw_mouse_delta();
+                pick(axis, [delta.x, delta.y]) * scale
+            }
+            Self::MouseWheel { axis, scale } => {
+                let delta = input.scroll_delta();
+                pick(axis, [delta.x, delta.y]) * scale
+            }
+            Self::Gamepad { axis, scale } => {
+                input
+                    .gamepads()
+                    .map(|(id, _)| input.gamepad_axis(id, *axis))
+                    .fold(0.0, |a: f32, b: f32| if b.abs() > a.abs() { b } else { a })
+                    * scale
+            }
+        }
+    }
+}
+
+/// Named actions and axes with the inputs bound to them, read by the
+/// [`super::ActionState`] every frame. Can be changed at runtime and saved
+/// back to a `.toml` or `.ron` file.
+///
+/// ```toml
+/// [actions]
+/// jump = [{ Key = { key = "Space" } }]
+/// save = [{ Key = { key = "S", modifiers = "CTRL" } }]
+///
+/// [axes]
+/// move_right = [{ Keys = { negative = "A", positive = "D" } }]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionMap {
+    #[serde(default)]
+    pub actions: BTreeMap<String, Vec<ActionBinding>>,
+    #[serde(default)]
+    pub axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl ActionMap {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ActionMapError> {
+        let path = path.as_ref();
+        let path_str = path.to_string_lossy().to_string();
+
+        let source = std::fs::read_to_string(path).map_err(|source| ActionMapError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&source),
+            Some("ron") => Self::from_ron_str(&source),
+            _ => return Err(ActionMapError::UnsupportedFormat(path_str)),
+        };
+
+        parsed.map_err(|source| ActionMapError::Parse {
+            path: path_str,
+            source,
+        })
+    }
+
+    pub fn from_toml_str(source: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn from_ron_str(source: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(source)?)
+    }
+
+    /// Writes the map in the format given by the extension of `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ActionMapError> {
+        let path = path.as_ref();
+        let path_str = path.to_string_lossy().to_string();
+
+        let serialized = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::to_string_pretty(self).map_err(anyhow::Error::from),
+            Some("ron") => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(anyhow::Error::from),
+            _ => return Err(ActionMapError::UnsupportedFormat(path_str)),
+        }
+        .map_err(ActionMapError::Serialize)?;
+
+        std::fs::write(path, serialized).map_err(|source| ActionMapError::Io {
+            path: path_str,
+            source,
+        })
+    }
+
+    /// Adds a binding to the action, keeping the existing ones
+    pub fn bind(&mut self, action: &str, binding: ActionBinding) {
+        let bindings = self.actions.entry(action.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Replaces every binding of the action
+    pub fn rebind(&mut self, action: &str, binding: ActionBinding) {
+        self.actions.insert(action.into(), vec![binding]);
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: &ActionBinding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|b| b != binding);
+        }
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        let bindings = self.axes.entry(axis.into()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn rebind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.insert(axis.into(), vec![binding]);
+    }
+
+    /// Binds the action only if the map has no entry for it, so the bindings
+    /// loaded from a file take precedence over the defaults of a layer
+    pub fn bind_default(&mut self, action: &str, bindings: &[ActionBinding]) {
+        self.actions
+            .entry(action.into())
+            .or_insert_with(|| bindings.to_vec());
+    }
+
+    pub fn bind_axis_default(&mut self, axis: &str, bindings: &[AxisBinding]) {
+        self.axes
+            .entry(axis.into())
+            .or_insert_with(|| bindings.to_vec());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> ActionMap {
+        let mut map = ActionMap::default();
+        map.bind("jump", ActionBinding::key(KeyboardKey::Space));
+        map.bind(
+            "save",
+            ActionBinding::Key {
+                key: KeyboardKey::S,
+                modifiers: KeyboardModifiers::CTRL,
+            },
+        );
+        map.bind("look", ActionBinding::mouse(MouseButton::Right));
+        map.bind_axis(
+            "move_right",
+            AxisBinding::Keys {
+                negative: KeyboardKey::A,
+                positive: KeyboardKey::D,
+            },
+        );
+        map.bind_axis(
+            "look_x",
+            AxisBinding::MouseMotion {
+                axis: MouseAxis::X,
+                scale: 0.1,
+            },
+        );
+        map
+    }
+
+    #[test]
+    fn round_trips_through_toml_and_ron() {
+        let map = map();
+
+        let toml = toml::to_string_pretty(&map).unwrap();
+        assert_eq!(ActionMap::from_toml_str(&toml).unwrap(), map);
+
+        let ron = ron::to_string(&map).unwrap();
+        assert_eq!(ActionMap::from_ron_str(&ron).unwrap(), map);
+    }
+
+    #[test]
+    fn modifiers_match_either_side() {
+        let mut input = InputHandler::new();
+        let save = map().actions["save"][0];
+
+        input.process_keyboard(KeyboardKey::S, true);
+        assert!(!save.is_pressed(&input));
+
+        input.process_keyboard(KeyboardKey::RCtrl, true);
+        input.process_keyboard(KeyboardKey::LShift, true);
+        assert!(save.is_pressed(&input));
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use super::{action_map::ActionMap, input_handler::InputHandler};
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DigitalState {
+    pressed: bool,
+    previous: bool,
+}
+
+/// Actions and axes of the [`ActionMap`] evaluated for the current frame.
+/// Unknown names read as released and zero.
+#[derive(Debug, Default)]
+pub struct ActionState {
+    actions: HashMap<String, DigitalState>,
+    axes: HashMap<String, f32>,
+}
+
+impl ActionState {
+    /// Evaluates the bindings against the input state, called once per frame
+    pub fn update(&mut self, map: &ActionMap, input: &InputHandler) {
+        self.actions
+            .retain(|name, _| map.actions.contains_key(name));
+        self.axes.retain(|name, _| map.axes.contains_key(name));
+
+        for (name, bindings) in &map.actions {
+            let state = self.actions.entry(name.clone()).or_default();
+            state.previous = state.pressed;
+            state.pressed = bindings.iter().any(|b| b.is_pressed(input));
+        }
+
+        for (name, bindings) in &map.axes {
+            let value = bindings.iter().map(|b| b.value(input)).sum();
+            self.axes.insert(name.clone(), value);
+        }
+    }
+
+    pub fn pressed(&self, action: &str) -> bool {
+        self.actions.get(action).is_some_and(|s| s.pressed)
+    }
+
+    /// Pressed this frame and released the one before
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.actions
+            .get(action)
+            .is_some_and(|s| s.pressed && !s.previous)
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.actions
+            .get(action)
+            .is_some_and(|s| !s.pressed && s.previous)
+    }
+
+    /// Sum of the bindings of an axis, or 1.0 for a pressed action
+    pub fn value(&self, name: &str) -> f32 {
+        match self.axes.get(name) {
+            Some(value) => *value,
+            None => self.pressed(name) as i32 as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{ActionBinding, AxisBinding, KeyboardKey};
+
+    #[test]
+    fn tracks_edges_and_axes() {
+        let mut map = ActionMap::default();
+        map.bind("jump", ActionBinding::key(KeyboardKey::Space));
+        map.bind_axis(
+            "move_right",
+            AxisBinding::Keys {
+                negative: KeyboardKey::A,
+                positive: KeyboardKey::D,
+            },
+        );
+
+        let mut input = InputHandler::new();
+        let mut state = ActionState::default();
+
+        input.process_keyboard(KeyboardKey::Space, true);
+        input.process_keyboard(KeyboardKey::A, true);
+        state.update(&map, &input);
+        assert!(state.just_pressed("jump"));
+        assert_eq!(state.value("move_right"), -1.0);
+
+        state.update(&map, &input);
+        assert!(state.pressed("jump") && !state.just_pressed("jump"));
+
+        input.process_keyboard(KeyboardKey::Space, false);
+        map.rebind("jump", ActionBinding::key(KeyboardKey::Enter));
+        state.update(&map, &input);
+        assert!(state.just_released("jump"));
+        assert_eq!(state.value("jump"), 0.0);
+        assert!(!state.pressed("unknown"));
+    }
+}
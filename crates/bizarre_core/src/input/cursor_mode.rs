@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// How the window treats the cursor, applied by the visual layer whenever
+/// the resource changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CursorMode {
+    #[default]
+    Free,
+    /// Kept inside the window
+    Confined,
+    /// Hidden and kept in place, read the movement from
+    /// [`super::InputHandler::raw_mouse_delta`]
+    Locked,
+}
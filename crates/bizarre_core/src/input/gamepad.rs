@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::input_handler::InputHandler;
+
+/// Assigned by the backend that reported the gamepad, or by
+/// [`VirtualGamepad::connect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct GamepadId(pub u32);
+
+/// Buttons named after their position, `South` is A on Xbox pads and Cross
+/// on PlayStation ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Other(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+    Other(u8),
+}
+
+#[derive(Debug, Clone)]
+pub enum GamepadEvent {
+    Connected {
+        id: GamepadId,
+        name: String,
+    },
+    Disconnected {
+        id: GamepadId,
+    },
+    ButtonPressed {
+        id: GamepadId,
+        button: GamepadButton,
+    },
+    ButtonReleased {
+        id: GamepadId,
+        button: GamepadButton,
+    },
+    /// Raw value, before the dead zone is applied
+    AxisChanged {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+/// Axis values below `inner` read as zero and values above `outer` as one,
+/// the range between them is rescaled to `0.0..1.0`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DeadZone {
+    pub inner: f32,
+    pub outer: f32,
+}
+
+impl Default for DeadZone {
+    fn default() -> Self {
+        Self {
+            inner: 0.1,
+            outer: 0.95,
+        }
+    }
+}
+
+impl DeadZone {
+    pub fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs();
+        if magnitude <= self.inner {
+            return 0.0;
+        }
+
+        let scaled = (magnitude - self.inner) / (self.outer - self.inner).max(f32::EPSILON);
+        scaled.min(1.0).copysign(value)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GamepadState {
+    pub name: String,
+    pub(crate) buttons: HashSet<GamepadButton>,
+    pub(crate) previous_buttons: HashSet<GamepadButton>,
+    pub(crate) axes: HashMap<GamepadAxis, f32>,
+}
+
+impl GamepadState {
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.contains(&button) && !self.previous_buttons.contains(&button)
+    }
+
+    pub fn just_released(&self, button: GamepadButton) -> bool {
+        !self.buttons.contains(&button) && self.previous_buttons.contains(&button)
+    }
+
+    /// Value without the dead zone applied
+    pub fn raw_axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
+
+/// Gamepad driven through code instead of hardware, for tests and tools.
+/// Its input goes through the same [`InputHandler`] calls a backend makes,
+/// so it is recorded like any other.
+#[derive(Debug)]
+pub struct VirtualGamepad {
+    id: GamepadId,
+}
+
+impl VirtualGamepad {
+    /// Ids of virtual gamepads start here to stay clear of the backend ones
+    pub const FIRST_ID: u32 = 1 << 16;
+
+    pub fn connect(input: &mut InputHandler, name: &str) -> Self {
+        let id = (Self::FIRST_ID..)
+            .map(GamepadId)
+            .find(|id| input.gamepad(*id).is_none())
+            .expect("Ran out of virtual gamepad ids");
+
+        input.process_gamepad_connected(id, name);
+        Self { id }
+    }
+
+    pub fn id(&self) -> GamepadId {
+        self.id
+    }
+
+    pub fn press(&self, input: &mut InputHandler, button: GamepadButton) {
+        input.process_gamepad_button(self.id, button, true);
+    }
+
+    pub fn release(&self, input: &mut InputHandler, button: GamepadButton) {
+        input.process_gamepad_button(self.id, button, false);
+    }
+
+    pub fn set_axis(&self, input: &mut InputHandler, axis: GamepadAxis, value: f32) {
+        input.process_gamepad_axis(self.id, axis, value);
+    }
+
+    pub fn disconnect(self, input: &mut InputHandler) {
+        input.process_gamepad_disconnected(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::shrev::EventChannel;
+
+    use super::*;
+
+    #[test]
+    fn virtual_gamepad_drives_the_input_handler() {
+        let mut input = InputHandler::new();
+        let mut events = EventChannel::<GamepadEvent>::new();
+        let mut reader = events.register_reader();
+
+        let pad = VirtualGamepad::connect(&mut input, "Virtual pad");
+        pad.press(&mut input, GamepadButton::South);
+        pad.set_axis(&mut input, GamepadAxis::LeftStickX, -0.05);
+        pad.set_axis(&mut input, GamepadAxis::LeftStickY, 1.0);
+
+        assert!(input.gamepad_button_just_pressed(pad.id(), GamepadButton::South));
+        assert_eq!(input.gamepad_axis(pad.id(), GamepadAxis::LeftStickX), 0.0);
+        assert_eq!(input.gamepad_axis(pad.id(), GamepadAxis::LeftStickY), 1.0);
+
+        input.update(&mut (
+            &mut EventChannel::new(),
+            &mut EventChannel::new(),
+            &mut events,
+            &mut EventChannel::new(),
+        ));
+        assert!(!input.gamepad_button_just_pressed(pad.id(), GamepadButton::South));
+        assert_eq!(events.read(&mut reader).count(), 4);
+
+        let id = pad.id();
+        pad.disconnect(&mut input);
+        assert!(input.gamepad(id).is_none());
+        assert!(!input.is_gamepad_button_pressed(id, GamepadButton::South));
+    }
+
+    #[test]
+    fn dead_zone_rescales_the_range() {
+        let dead_zone = DeadZone {
+            inner: 0.2,
+            outer: 0.8,
+        };
+
+        assert_eq!(dead_zone.apply(0.1), 0.0);
+        assert!((dead_zone.apply(-0.5) + 0.5).abs() < 1e-6);
+        assert_eq!(dead_zone.apply(0.9), 1.0);
+    }
+}
@@ -1,9 +1,14 @@
 use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
 
-use super::{key_codes::KeyboardKey, mouse_button::MouseButton};
+use super::{
+    key_codes::{KeyboardKey, LogicalKey},
+    mouse_button::MouseButton,
+};
 
 bitflags! {
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    #[serde(transparent)]
     pub struct KeyboardModifiers : u8 {
         const NONE = 0b00000000;
 
@@ -24,6 +29,44 @@ bitflags! {
     }
 }
 
+/// How the held modifiers are compared with the required ones. A side
+/// specific modifier like `L_CTRL` has to be held on that side, while `CTRL`
+/// is satisfied by either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ModifierMatch {
+    /// The required modifiers are held and no others
+    Exact,
+    /// The required modifiers are held, others may be too
+    Subset,
+    /// Modifiers don't matter
+    Ignore,
+}
+
+impl KeyboardModifiers {
+    const GROUPS: [KeyboardModifiers; 4] = [Self::SHIFT, Self::CTRL, Self::ALT, Self::SUPER];
+
+    /// Whether `held` satisfies `self` as the required modifiers
+    pub fn matches(self, held: KeyboardModifiers, mode: ModifierMatch) -> bool {
+        let required_held = || {
+            Self::GROUPS.into_iter().all(|group| {
+                let required = self & group;
+                required.is_empty() || held.intersects(required)
+            })
+        };
+
+        match mode {
+            ModifierMatch::Ignore => true,
+            ModifierMatch::Subset => required_held(),
+            ModifierMatch::Exact => {
+                required_held()
+                    && Self::GROUPS
+                        .into_iter()
+                        .all(|group| self.intersects(group) || !held.intersects(group))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum MouseEvent {
     Pressed {
@@ -48,10 +91,32 @@ pub enum MouseEvent {
 pub enum KeyboardEvent {
     Pressed {
         key: KeyboardKey,
+        logical: LogicalKey,
         modifiers: KeyboardModifiers,
     },
     Released {
         key: KeyboardKey,
+        logical: LogicalKey,
         modifiers: KeyboardModifiers,
     },
+    /// Sent by the OS while the key is held, the key stays pressed
+    Repeated {
+        key: KeyboardKey,
+        logical: LogicalKey,
+        modifiers: KeyboardModifiers,
+    },
+}
+
+/// Text typed while text input is enabled, see
+/// [`super::InputHandler::set_text_input`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextInputEvent {
+    /// Typed characters or a finished IME composition
+    Commit(String),
+    /// IME composition in progress, `cursor` is a byte range into `text`.
+    /// An empty `text` means the composition was cleared.
+    Preedit {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
 }
@@ -1,22 +1,46 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
 use bizarre_logger::core_debug;
 use nalgebra_glm::Vec2;
 use specs::shrev::EventChannel;
 
-use crate::input::{key_codes::KeyboardKey, KeyboardEvent};
+use crate::input::{
+    key_codes::{KeySet, KeyboardKey, LogicalKey},
+    KeyboardEvent,
+};
 
-use super::{mouse_button::MouseButton, KeyboardModifiers, MouseEvent};
+use super::{
+    gamepad::{DeadZone, GamepadAxis, GamepadButton, GamepadEvent, GamepadId, GamepadState},
+    input_recording::RawInput,
+    mouse_button::MouseButton,
+    KeyboardModifiers, ModifierMatch, MouseEvent, TextInputEvent,
+};
 
 pub struct InputHandler {
     mouse_previous_position: Vec2,
     mouse_position: Vec2,
     mouse_wheel_delta: Vec2,
+    raw_mouse_delta: Vec2,
     keyboard_modifiers: KeyboardModifiers,
-    keyboard_state: [bool; u16::MAX as usize],
-    previous_keyboard_state: [bool; u16::MAX as usize],
+    keyboard_state: KeySet,
+    previous_keyboard_state: KeySet,
     mouse_button_state: [bool; u8::MAX as usize],
     previous_mouse_button_state: [bool; u8::MAX as usize],
+    key_press_times: HashMap<KeyboardKey, Instant>,
+    logical_keys: HashMap<KeyboardKey, LogicalKey>,
+    button_press_times: HashMap<u8, Instant>,
+    frame_inputs: Vec<RawInput>,
+    gamepads: BTreeMap<GamepadId, GamepadState>,
+    text_input_enabled: bool,
+    preedit: Option<(String, Option<(usize, usize)>)>,
+    pub gamepad_dead_zone: DeadZone,
     pub local_keyboard_eq: Vec<KeyboardEvent>,
     pub local_mouse_eq: Vec<MouseEvent>,
+    pub local_gamepad_eq: Vec<GamepadEvent>,
+    pub local_text_eq: Vec<TextInputEvent>,
 }
 
 impl Default for InputHandler {
@@ -31,18 +55,80 @@ impl InputHandler {
             mouse_position: [0.0, 0.0].into(),
             mouse_previous_position: [0.0, 0.0].into(),
             mouse_wheel_delta: [0.0, 0.0].into(),
+            raw_mouse_delta: [0.0, 0.0].into(),
             keyboard_modifiers: KeyboardModifiers::NONE,
-            keyboard_state: [false; u16::MAX as usize],
-            previous_keyboard_state: [false; u16::MAX as usize],
+            keyboard_state: KeySet::default(),
+            previous_keyboard_state: KeySet::default(),
             mouse_button_state: [false; u8::MAX as usize],
             previous_mouse_button_state: [false; u8::MAX as usize],
+            key_press_times: HashMap::new(),
+            logical_keys: HashMap::new(),
+            button_press_times: HashMap::new(),
+            frame_inputs: Vec::new(),
+            gamepads: BTreeMap::new(),
+            text_input_enabled: false,
+            preedit: None,
+            gamepad_dead_zone: DeadZone::default(),
             local_keyboard_eq: Vec::default(),
             local_mouse_eq: Vec::default(),
+            local_gamepad_eq: Vec::default(),
+            local_text_eq: Vec::default(),
+        }
+    }
+
+    /// Feeds a recorded input as if it came from the device
+    pub fn apply(&mut self, input: &RawInput) {
+        match *input {
+            RawInput::Keyboard {
+                key,
+                ref logical,
+                pressed,
+            } => self.process_keyboard_with_logical(key, logical.clone(), pressed),
+            RawInput::KeyRepeat { key } => self.process_key_repeat(key),
+            RawInput::Text { ref text } => self.process_text(text),
+            RawInput::Preedit { ref text, cursor } => self.process_preedit(text, cursor),
+            RawInput::MouseMove { x, y } => self.process_mouse_move([x, y].into()),
+            RawInput::RawMouseMotion { x, y } => self.process_raw_mouse_motion([x, y]),
+            RawInput::MouseButton { button, pressed } => self.process_mouse_button(button, pressed),
+            RawInput::MouseScroll { x, y } => self.process_mouse_scroll([x, y]),
+            RawInput::GamepadConnected { id, ref name } => self.process_gamepad_connected(id, name),
+            RawInput::GamepadDisconnected { id } => self.process_gamepad_disconnected(id),
+            RawInput::GamepadButton {
+                id,
+                button,
+                pressed,
+            } => self.process_gamepad_button(id, button, pressed),
+            RawInput::GamepadAxis { id, axis, value } => self.process_gamepad_axis(id, axis, value),
         }
     }
 
-    pub fn process_keyboard(&mut self, keycode: u16, pressed: bool) {
-        let key = KeyboardKey::from(keycode);
+    /// Raw inputs processed since the last [`InputHandler::update`]
+    pub fn frame_inputs(&self) -> &[RawInput] {
+        &self.frame_inputs
+    }
+
+    pub fn process_keyboard(&mut self, key: KeyboardKey, pressed: bool) {
+        self.process_keyboard_with_logical(key, LogicalKey::Unidentified, pressed);
+    }
+
+    /// Key event along with what the key produces under the active layout,
+    /// which [`Self::key_label`] remembers
+    pub fn process_keyboard_with_logical(
+        &mut self,
+        key: KeyboardKey,
+        logical: LogicalKey,
+        pressed: bool,
+    ) {
+        self.frame_inputs.push(RawInput::Keyboard {
+            key,
+            logical: logical.clone(),
+            pressed,
+        });
+
+        if !logical.is_unidentified() {
+            self.logical_keys.insert(key, logical);
+        }
+
         macro_rules! process_modifiers {
             {$($key:ident => $modifier:ident),+,} => {
                 match key {
@@ -71,16 +157,25 @@ impl InputHandler {
             RSuper => R_SUPER,
         }
 
-        self.keyboard_state[keycode as usize] = pressed;
+        if pressed {
+            self.key_press_times.entry(key).or_insert_with(Instant::now);
+        } else {
+            self.key_press_times.remove(&key);
+        }
 
+        self.keyboard_state.set(key, pressed);
+
+        let logical = self.logical_key(&key);
         let event = if pressed {
             KeyboardEvent::Pressed {
                 key,
+                logical,
                 modifiers: self.keyboard_modifiers,
             }
         } else {
             KeyboardEvent::Released {
                 key,
+                logical,
                 modifiers: self.keyboard_modifiers,
             }
         };
@@ -88,7 +183,89 @@ impl InputHandler {
         self.local_keyboard_eq.push(event);
     }
 
+    /// Repeats of a key that isn't held are dropped
+    pub fn process_key_repeat(&mut self, key: KeyboardKey) {
+        self.frame_inputs.push(RawInput::KeyRepeat { key });
+
+        if !self.keyboard_state.contains(key) {
+            return;
+        }
+
+        self.local_keyboard_eq.push(KeyboardEvent::Repeated {
+            key,
+            logical: self.logical_key(&key),
+            modifiers: self.keyboard_modifiers,
+        });
+    }
+
+    /// Committed text, dropped while text input is disabled. Control
+    /// characters are left to the key events.
+    pub fn process_text(&mut self, text: &str) {
+        self.frame_inputs.push(RawInput::Text { text: text.into() });
+
+        if !self.text_input_enabled {
+            return;
+        }
+
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if !text.is_empty() {
+            self.local_text_eq.push(TextInputEvent::Commit(text));
+        }
+    }
+
+    /// IME composition, an empty `text` clears it
+    pub fn process_preedit(&mut self, text: &str, cursor: Option<(usize, usize)>) {
+        self.frame_inputs.push(RawInput::Preedit {
+            text: text.into(),
+            cursor,
+        });
+
+        if !self.text_input_enabled {
+            return;
+        }
+
+        self.preedit = if text.is_empty() {
+            None
+        } else {
+            Some((text.into(), cursor))
+        };
+
+        self.local_text_eq.push(TextInputEvent::Preedit {
+            text: text.into(),
+            cursor,
+        });
+    }
+
+    /// Turns on [`TextInputEvent`]s and the IME of the window, for text
+    /// fields. Key events keep coming in either way.
+    pub fn set_text_input(&mut self, enabled: bool) {
+        if !enabled && self.preedit.take().is_some() {
+            self.local_text_eq.push(TextInputEvent::Preedit {
+                text: String::new(),
+                cursor: None,
+            });
+        }
+
+        self.text_input_enabled = enabled;
+    }
+
+    pub fn is_text_input_enabled(&self) -> bool {
+        self.text_input_enabled
+    }
+
+    /// IME composition in progress and its cursor
+    pub fn preedit(&self) -> Option<(&str, Option<(usize, usize)>)> {
+        self.preedit
+            .as_ref()
+            .map(|(text, cursor)| (text.as_str(), *cursor))
+    }
+
     pub fn process_mouse_move(&mut self, position: Vec2) {
+        self.frame_inputs.push(RawInput::MouseMove {
+            x: position.x,
+            y: position.y,
+        });
+
         self.mouse_position = position;
 
         let event = MouseEvent::Moved {
@@ -99,10 +276,32 @@ impl InputHandler {
         self.local_mouse_eq.push(event);
     }
 
+    /// Device movement, in device units that don't stop at the window edge
+    pub fn process_raw_mouse_motion(&mut self, delta: [f32; 2]) {
+        self.frame_inputs.push(RawInput::RawMouseMotion {
+            x: delta[0],
+            y: delta[1],
+        });
+
+        self.raw_mouse_delta[0] += delta[0];
+        self.raw_mouse_delta[1] += delta[1];
+    }
+
     pub fn process_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        self.frame_inputs
+            .push(RawInput::MouseButton { button, pressed });
+
         let index: u8 = button.into();
         self.mouse_button_state[index as usize] = pressed;
 
+        if pressed {
+            self.button_press_times
+                .entry(index)
+                .or_insert_with(Instant::now);
+        } else {
+            self.button_press_times.remove(&index);
+        }
+
         let event = if pressed {
             MouseEvent::Pressed {
                 button,
@@ -119,15 +318,88 @@ impl InputHandler {
     }
 
     pub fn process_mouse_scroll(&mut self, delta: [f32; 2]) {
+        self.frame_inputs.push(RawInput::MouseScroll {
+            x: delta[0],
+            y: delta[1],
+        });
+
         self.mouse_wheel_delta[0] += delta[0];
         self.mouse_wheel_delta[1] += delta[1];
     }
 
+    /// Connecting an already connected gamepad only renames it
+    pub fn process_gamepad_connected(&mut self, id: GamepadId, name: &str) {
+        self.frame_inputs.push(RawInput::GamepadConnected {
+            id,
+            name: name.into(),
+        });
+
+        core_debug!("Gamepad {:?} connected: {}", id, name);
+
+        self.gamepads.entry(id).or_default().name = name.into();
+        self.local_gamepad_eq.push(GamepadEvent::Connected {
+            id,
+            name: name.into(),
+        });
+    }
+
+    pub fn process_gamepad_disconnected(&mut self, id: GamepadId) {
+        self.frame_inputs.push(RawInput::GamepadDisconnected { id });
+
+        if self.gamepads.remove(&id).is_some() {
+            core_debug!("Gamepad {:?} disconnected", id);
+            self.local_gamepad_eq
+                .push(GamepadEvent::Disconnected { id });
+        }
+    }
+
+    /// Input of unknown gamepads is dropped, they have to be connected first
+    pub fn process_gamepad_button(&mut self, id: GamepadId, button: GamepadButton, pressed: bool) {
+        self.frame_inputs.push(RawInput::GamepadButton {
+            id,
+            button,
+            pressed,
+        });
+
+        let gamepad = match self.gamepads.get_mut(&id) {
+            Some(gamepad) => gamepad,
+            None => return,
+        };
+
+        let event = if pressed {
+            gamepad.buttons.insert(button);
+            GamepadEvent::ButtonPressed { id, button }
+        } else {
+            gamepad.buttons.remove(&button);
+            GamepadEvent::ButtonReleased { id, button }
+        };
+
+        self.local_gamepad_eq.push(event);
+    }
+
+    pub fn process_gamepad_axis(&mut self, id: GamepadId, axis: GamepadAxis, value: f32) {
+        self.frame_inputs
+            .push(RawInput::GamepadAxis { id, axis, value });
+
+        let gamepad = match self.gamepads.get_mut(&id) {
+            Some(gamepad) => gamepad,
+            None => return,
+        };
+
+        let value = value.clamp(-1.0, 1.0);
+        if gamepad.axes.insert(axis, value) != Some(value) {
+            self.local_gamepad_eq
+                .push(GamepadEvent::AxisChanged { id, axis, value });
+        }
+    }
+
     pub fn update(
         &mut self,
         event_queues: &mut (
             &mut EventChannel<MouseEvent>,
             &mut EventChannel<KeyboardEvent>,
+            &mut EventChannel<GamepadEvent>,
+            &mut EventChannel<TextInputEvent>,
         ),
     ) {
         if self.mouse_wheel_delta[0] != 0.0 || self.mouse_wheel_delta[1] != 0.0 {
@@ -139,24 +411,167 @@ impl InputHandler {
             self.local_mouse_eq.push(event);
         }
         self.mouse_wheel_delta = [0.0, 0.0].into();
+        self.raw_mouse_delta = [0.0, 0.0].into();
         self.mouse_previous_position = self.mouse_position;
-        self.previous_keyboard_state = self.keyboard_state;
+        self.previous_keyboard_state
+            .clone_from(&self.keyboard_state);
         self.previous_mouse_button_state = self.mouse_button_state;
+        self.frame_inputs.clear();
+
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.previous_buttons.clone_from(&gamepad.buttons);
+        }
 
-        let (mouse, keyboard) = event_queues;
+        let (mouse, keyboard, gamepad, text) = event_queues;
 
         mouse.drain_vec_write(&mut self.local_mouse_eq);
         keyboard.drain_vec_write(&mut self.local_keyboard_eq);
+        gamepad.drain_vec_write(&mut self.local_gamepad_eq);
+        text.drain_vec_write(&mut self.local_text_eq);
+    }
+
+    /// Ends the frame like [`InputHandler::update`], dropping the events
+    #[cfg(test)]
+    pub(crate) fn end_frame_discarding_events(&mut self) {
+        self.update(&mut (
+            &mut EventChannel::new(),
+            &mut EventChannel::new(),
+            &mut EventChannel::new(),
+            &mut EventChannel::new(),
+        ));
     }
 
+    pub fn gamepads(&self) -> impl Iterator<Item = (GamepadId, &GamepadState)> {
+        self.gamepads.iter().map(|(id, state)| (*id, state))
+    }
+
+    pub fn gamepad(&self, id: GamepadId) -> Option<&GamepadState> {
+        self.gamepads.get(&id)
+    }
+
+    pub fn is_gamepad_button_pressed(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id).is_some_and(|g| g.is_pressed(button))
+    }
+
+    pub fn gamepad_button_just_pressed(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id).is_some_and(|g| g.just_pressed(button))
+    }
+
+    pub fn gamepad_button_just_released(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepad(id).is_some_and(|g| g.just_released(button))
+    }
+
+    /// Axis value with the dead zone applied, zero for unknown gamepads
+    pub fn gamepad_axis(&self, id: GamepadId, axis: GamepadAxis) -> f32 {
+        self.gamepad(id)
+            .map_or(0.0, |g| self.gamepad_dead_zone.apply(g.raw_axis(axis)))
+    }
+
+    /// Whether the key is held along with exactly these modifiers
     pub fn is_key_pressed(&self, key: &KeyboardKey, modifiers: &KeyboardModifiers) -> bool {
-        self.keyboard_state[u16::from(*key) as usize]
-            && self.keyboard_modifiers.bits() == modifiers.bits()
+        self.is_key_pressed_matching(key, modifiers, ModifierMatch::Exact)
     }
 
+    /// Whether the button is held along with exactly these modifiers
     pub fn is_button_pressed(&self, button: &MouseButton, modifiers: &KeyboardModifiers) -> bool {
+        self.is_button_pressed_matching(button, modifiers, ModifierMatch::Exact)
+    }
+
+    pub fn is_key_pressed_matching(
+        &self,
+        key: &KeyboardKey,
+        modifiers: &KeyboardModifiers,
+        mode: ModifierMatch,
+    ) -> bool {
+        self.is_key_down(key) && self.modifiers_match(modifiers, mode)
+    }
+
+    pub fn is_button_pressed_matching(
+        &self,
+        button: &MouseButton,
+        modifiers: &KeyboardModifiers,
+        mode: ModifierMatch,
+    ) -> bool {
+        self.is_button_down(button) && self.modifiers_match(modifiers, mode)
+    }
+
+    pub fn modifiers_match(&self, modifiers: &KeyboardModifiers, mode: ModifierMatch) -> bool {
+        modifiers.matches(self.keyboard_modifiers, mode)
+    }
+
+    /// Pressed this frame and not the one before, whatever the modifiers
+    pub fn just_pressed(&self, key: &KeyboardKey) -> bool {
+        self.keyboard_state.contains(*key) && !self.previous_keyboard_state.contains(*key)
+    }
+
+    pub fn just_released(&self, key: &KeyboardKey) -> bool {
+        !self.keyboard_state.contains(*key) && self.previous_keyboard_state.contains(*key)
+    }
+
+    /// Chorded shortcuts, e.g. Ctrl+S with [`KeyboardModifiers::CTRL`] and
+    /// [`ModifierMatch::Exact`]
+    pub fn just_pressed_with(
+        &self,
+        key: &KeyboardKey,
+        modifiers: &KeyboardModifiers,
+        mode: ModifierMatch,
+    ) -> bool {
+        self.just_pressed(key) && self.modifiers_match(modifiers, mode)
+    }
+
+    pub fn button_just_pressed(&self, button: &MouseButton) -> bool {
+        let index = u8::from(*button) as usize;
+        self.mouse_button_state[index] && !self.previous_mouse_button_state[index]
+    }
+
+    pub fn button_just_released(&self, button: &MouseButton) -> bool {
+        let index = u8::from(*button) as usize;
+        !self.mouse_button_state[index] && self.previous_mouse_button_state[index]
+    }
+
+    /// How long the key has been held, `None` if it isn't
+    pub fn key_held_duration(&self, key: &KeyboardKey) -> Option<Duration> {
+        self.key_press_times.get(key).map(Instant::elapsed)
+    }
+
+    pub fn button_held_duration(&self, button: &MouseButton) -> Option<Duration> {
+        self.button_press_times
+            .get(&u8::from(*button))
+            .map(Instant::elapsed)
+    }
+
+    /// Whether the key is held, whatever the modifiers
+    pub fn is_key_down(&self, key: &KeyboardKey) -> bool {
+        self.keyboard_state.contains(*key)
+    }
+
+    pub fn pressed_keys(&self) -> impl Iterator<Item = KeyboardKey> + '_ {
+        self.keyboard_state.iter()
+    }
+
+    /// What the key produced under the active layout when last pressed
+    pub fn logical_key(&self, key: &KeyboardKey) -> LogicalKey {
+        self.logical_keys.get(key).cloned().unwrap_or_default()
+    }
+
+    /// Name of the key for prompts and settings menus, e.g. `Z` for
+    /// [`KeyboardKey::Y`] on a German layout. The layout is learned as keys
+    /// get pressed, until then the physical name is used.
+    pub fn key_label(&self, key: &KeyboardKey) -> String {
+        match self.logical_keys.get(key) {
+            Some(LogicalKey::Character(text)) => text.to_uppercase(),
+            Some(LogicalKey::Named(name)) => name.clone(),
+            _ => key.to_string(),
+        }
+    }
+
+    /// Whether the button is held, whatever the modifiers
+    pub fn is_button_down(&self, button: &MouseButton) -> bool {
         self.mouse_button_state[u8::from(*button) as usize]
-            && self.keyboard_modifiers.bits() == modifiers.bits()
+    }
+
+    pub fn modifiers(&self) -> KeyboardModifiers {
+        self.keyboard_modifiers
     }
 
     pub fn mouse_delta(&self) -> Vec2 {
@@ -166,7 +581,125 @@ impl InputHandler {
         delta
     }
 
+    /// Device movement of this frame, use it over [`Self::mouse_delta`] for
+    /// mouse look
+    pub fn raw_mouse_delta(&self) -> Vec2 {
+        self.raw_mouse_delta
+    }
+
     pub fn scroll_delta(&self) -> Vec2 {
         self.mouse_wheel_delta
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_edges_and_hold_duration() {
+        let mut input = InputHandler::new();
+
+        input.process_keyboard(KeyboardKey::Space, true);
+        assert!(input.just_pressed(&KeyboardKey::Space));
+        assert!(input.key_held_duration(&KeyboardKey::Space).is_some());
+
+        input.end_frame_discarding_events();
+        input.process_keyboard(KeyboardKey::Space, true);
+        assert!(!input.just_pressed(&KeyboardKey::Space));
+
+        input.end_frame_discarding_events();
+        input.process_keyboard(KeyboardKey::Space, false);
+        assert!(input.just_released(&KeyboardKey::Space));
+        assert_eq!(input.key_held_duration(&KeyboardKey::Space), None);
+    }
+
+    #[test]
+    fn matches_modifiers() {
+        let mut input = InputHandler::new();
+        input.process_keyboard(KeyboardKey::LCtrl, true);
+        input.process_keyboard(KeyboardKey::LShift, true);
+        input.process_keyboard(KeyboardKey::S, true);
+
+        let s = KeyboardKey::S;
+        let ctrl = KeyboardModifiers::CTRL;
+        let ctrl_shift = KeyboardModifiers::CTRL | KeyboardModifiers::L_SHIFT;
+
+        assert!(!input.just_pressed_with(&s, &ctrl, ModifierMatch::Exact));
+        assert!(input.just_pressed_with(&s, &ctrl, ModifierMatch::Subset));
+        assert!(input.just_pressed_with(&s, &ctrl_shift, ModifierMatch::Exact));
+        assert!(!input.is_key_pressed_matching(
+            &s,
+            &KeyboardModifiers::R_CTRL,
+            ModifierMatch::Subset
+        ));
+        assert!(input.is_key_pressed_matching(&s, &KeyboardModifiers::ALT, ModifierMatch::Ignore));
+    }
+
+    #[test]
+    fn text_input_is_gated_and_repeats_keep_the_key_down() {
+        let mut input = InputHandler::new();
+
+        input.process_text("a");
+        assert!(input.local_text_eq.is_empty());
+
+        input.set_text_input(true);
+        input.process_text("b\r");
+        input.process_preedit("に", Some((0, 3)));
+        assert_eq!(input.preedit(), Some(("に", Some((0, 3)))));
+
+        input.set_text_input(false);
+        assert_eq!(input.preedit(), None);
+        assert_eq!(
+            input.local_text_eq,
+            vec![
+                TextInputEvent::Commit("b".into()),
+                TextInputEvent::Preedit {
+                    text: "に".into(),
+                    cursor: Some((0, 3)),
+                },
+                TextInputEvent::Preedit {
+                    text: String::new(),
+                    cursor: None,
+                },
+            ]
+        );
+
+        input.process_keyboard(KeyboardKey::A, true);
+        input.end_frame_discarding_events();
+        input.process_key_repeat(KeyboardKey::A);
+        assert!(input.is_key_down(&KeyboardKey::A));
+        assert!(!input.just_pressed(&KeyboardKey::A));
+        assert!(matches!(
+            input.local_keyboard_eq.last(),
+            Some(KeyboardEvent::Repeated { .. })
+        ));
+    }
+
+    #[test]
+    fn raw_mouse_motion_accumulates_per_frame() {
+        let mut input = InputHandler::new();
+
+        input.process_raw_mouse_motion([3.0, -1.0]);
+        input.process_raw_mouse_motion([2.0, 4.0]);
+        assert_eq!(input.raw_mouse_delta(), Vec2::new(5.0, 3.0));
+        assert_eq!(input.mouse_delta(), Vec2::zeros());
+
+        input.end_frame_discarding_events();
+        assert_eq!(input.raw_mouse_delta(), Vec2::zeros());
+    }
+
+    #[test]
+    fn labels_keys_after_the_layout() {
+        let mut input = InputHandler::new();
+        assert_eq!(input.key_label(&KeyboardKey::Y), "Y");
+
+        let z = LogicalKey::Character("z".into());
+        input.process_keyboard_with_logical(KeyboardKey::Y, z.clone(), true);
+        assert_eq!(input.key_label(&KeyboardKey::Y), "Z");
+        assert!(matches!(
+            input.local_keyboard_eq.last(),
+            Some(KeyboardEvent::Pressed { logical, .. }) if *logical == z
+        ));
+    }
+}
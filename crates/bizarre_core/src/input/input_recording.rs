@@ -0,0 +1,269 @@
+use std::{path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    gamepad::{GamepadAxis, GamepadButton, GamepadId},
+    input_handler::InputHandler,
+    key_codes::{KeyboardKey, LogicalKey},
+    mouse_button::MouseButton,
+};
+
+#[derive(Debug, Error)]
+pub enum InputRecordingError {
+    #[error("could not access the input recording '{path}': {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("could not parse the input recording '{path}': {source}")]
+    Parse { path: String, source: anyhow::Error },
+
+    #[error("could not serialize the input recording: {0}")]
+    Serialize(anyhow::Error),
+}
+
+/// Input as fed to the [`InputHandler`] by the window and gamepad backends
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RawInput {
+    Keyboard {
+        key: KeyboardKey,
+        #[serde(default, skip_serializing_if = "LogicalKey::is_unidentified")]
+        logical: LogicalKey,
+        pressed: bool,
+    },
+    KeyRepeat {
+        key: KeyboardKey,
+    },
+    Text {
+        text: String,
+    },
+    Preedit {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
+    MouseMove {
+        x: f32,
+        y: f32,
+    },
+    /// Device movement, not tied to the cursor
+    RawMouseMotion {
+        x: f32,
+        y: f32,
+    },
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+    },
+    MouseScroll {
+        x: f32,
+        y: f32,
+    },
+    GamepadConnected {
+        id: GamepadId,
+        name: String,
+    },
+    GamepadDisconnected {
+        id: GamepadId,
+    },
+    GamepadButton {
+        id: GamepadId,
+        button: GamepadButton,
+        pressed: bool,
+    },
+    GamepadAxis {
+        id: GamepadId,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// `DeltaTime` of the frame
+    pub delta: Duration,
+    pub inputs: Vec<RawInput>,
+}
+
+/// Raw input of consecutive frames, stored in the RON format
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputRecording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, InputRecordingError> {
+        let path_str = path.as_ref().to_string_lossy().to_string();
+
+        let source = std::fs::read_to_string(path).map_err(|source| InputRecordingError::Io {
+            path: path_str.clone(),
+            source,
+        })?;
+
+        Self::from_ron_str(&source).map_err(|source| InputRecordingError::Parse {
+            path: path_str,
+            source,
+        })
+    }
+
+    pub fn from_ron_str(source: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(source)?)
+    }
+
+    pub fn to_ron_string(&self) -> anyhow::Result<String> {
+        Ok(ron::to_string(self)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), InputRecordingError> {
+        let serialized = self
+            .to_ron_string()
+            .map_err(InputRecordingError::Serialize)?;
+
+        std::fs::write(&path, serialized).map_err(|source| InputRecordingError::Io {
+            path: path.as_ref().to_string_lossy().to_string(),
+            source,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|f| f.delta).sum()
+    }
+}
+
+/// Records the raw input of every frame while started
+#[derive(Debug, Default)]
+pub struct InputRecorder {
+    recording: InputRecording,
+    active: bool,
+}
+
+impl InputRecorder {
+    pub fn start(&mut self) {
+        self.active = true;
+    }
+
+    /// Stops recording and returns the frames recorded so far
+    pub fn stop(&mut self) -> InputRecording {
+        self.active = false;
+        std::mem::take(&mut self.recording)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active
+    }
+
+    pub fn recording(&self) -> &InputRecording {
+        &self.recording
+    }
+
+    /// Called once per frame, after the window events were processed
+    pub fn record_frame(&mut self, input: &InputHandler, delta: Duration) {
+        if !self.active {
+            return;
+        }
+
+        self.recording.frames.push(RecordedFrame {
+            delta,
+            inputs: input.frame_inputs().to_vec(),
+        });
+    }
+}
+
+/// Feeds a recording to the [`InputHandler`] frame by frame. The window
+/// backend ignores the devices until it finishes, and
+/// [`App::update`](crate::App::update) advances the time by the recorded
+/// deltas instead of the measured ones.
+///
+/// For deterministic headless runs step the app with the recorded deltas:
+///
+/// ```ignore
+/// for frame in &recording.frames {
+///     app.step(frame.delta);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct InputPlayer {
+    recording: InputRecording,
+    next_frame: usize,
+}
+
+impl InputPlayer {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            next_frame: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_frame >= self.recording.frames.len()
+    }
+
+    pub fn frames_left(&self) -> usize {
+        self.recording.frames.len().saturating_sub(self.next_frame)
+    }
+
+    /// Delta of the next frame, `None` once the recording is over
+    pub fn next_delta(&self) -> Option<Duration> {
+        self.recording
+            .frames
+            .get(self.next_frame)
+            .map(|frame| frame.delta)
+    }
+
+    /// Applies the inputs of the next frame and returns its delta, `None`
+    /// once the recording is over
+    pub fn play_frame(&mut self, input: &mut InputHandler) -> Option<Duration> {
+        let frame = self.recording.frames.get(self.next_frame)?;
+        self.next_frame += 1;
+
+        for raw in &frame.inputs {
+            input.apply(raw);
+        }
+
+        Some(frame.delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_the_recorded_input() {
+        let delta = Duration::from_millis(16);
+        let mut input = InputHandler::new();
+        let mut recorder = InputRecorder::default();
+        recorder.start();
+
+        input.process_keyboard(KeyboardKey::W, true);
+        input.process_mouse_move([10.0, 20.0].into());
+        recorder.record_frame(&input, delta);
+        input.end_frame_discarding_events();
+
+        input.process_mouse_button(MouseButton::Left, true);
+        recorder.record_frame(&input, delta);
+
+        let recording = recorder.stop();
+        let recording = InputRecording::from_ron_str(&recording.to_ron_string().unwrap()).unwrap();
+        assert_eq!(recording.frames[0].inputs.len(), 2);
+        assert_eq!(recording.duration(), delta * 2);
+
+        let mut replayed = InputHandler::new();
+        let mut player = InputPlayer::new(recording);
+        assert_eq!(player.next_delta(), Some(delta));
+
+        assert_eq!(player.play_frame(&mut replayed), Some(delta));
+        replayed.end_frame_discarding_events();
+        assert_eq!(player.play_frame(&mut replayed), Some(delta));
+        assert_eq!(player.next_delta(), None);
+        assert_eq!(player.play_frame(&mut replayed), None);
+        assert!(player.is_finished());
+
+        assert!(replayed.is_key_down(&KeyboardKey::W));
+        assert!(replayed.button_just_pressed(&MouseButton::Left));
+        assert_eq!(replayed.frame_inputs(), input.frame_inputs());
+    }
+}
@@ -1,6 +1,13 @@
+use std::any::TypeId;
+
 use anyhow::Result;
+use thiserror::Error;
 
-use crate::{app_builder::AppBuilder, schedule::ScheduleBuilder};
+use crate::{
+    app_builder::AppBuilder,
+    app_state::StateTransitionDriver,
+    schedule::{Schedule, ScheduleError},
+};
 
 pub trait Layer {
     fn on_attach(&mut self, app_builder: &mut AppBuilder) -> Result<()> {
@@ -8,9 +15,142 @@ pub trait Layer {
         Ok(())
     }
 
+    /// Called when the layer is popped or the app is destroyed. Layers are
+    /// detached in the reverse order of attaching.
+    fn on_detach(&mut self, world: &mut specs::World) -> Result<()> {
+        let _ = world;
+        Ok(())
+    }
+
+    /// Layers that have to be attached before this one
+    fn dependencies(&self) -> Vec<LayerDependency> {
+        Vec::new()
+    }
+
     #[deprecated]
     fn on_update(&mut self, world: &mut specs::World) -> Result<()> {
         let _ = world;
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerDependency {
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+}
+
+impl LayerDependency {
+    pub fn of<L: Layer + 'static>() -> Self {
+        Self {
+            type_id: TypeId::of::<L>(),
+            type_name: std::any::type_name::<L>(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LayerError {
+    #[error("layer {layer} requires {dependency} to be attached before it")]
+    MissingDependency {
+        layer: &'static str,
+        dependency: &'static str,
+    },
+
+    #[error("layer {0} is attached more than once")]
+    AlreadyAttached(&'static str),
+
+    #[error("failed to attach layer {layer}: {source}")]
+    AttachFailed {
+        layer: &'static str,
+        source: anyhow::Error,
+    },
+
+    #[error(transparent)]
+    Schedule(#[from] ScheduleError),
+}
+
+pub struct AttachedLayer {
+    pub(crate) layer: Box<dyn Layer>,
+    pub(crate) type_id: TypeId,
+    pub(crate) type_name: &'static str,
+    /// Systems of layers pushed at runtime, layers attached by the builder
+    /// share the app schedule
+    pub(crate) schedule: Option<Schedule>,
+    pub(crate) state_drivers: Vec<Box<dyn StateTransitionDriver>>,
+}
+
+impl AttachedLayer {
+    pub(crate) fn new<L: Layer + 'static>(layer: L) -> Self {
+        Self {
+            layer: Box::new(layer),
+            type_id: TypeId::of::<L>(),
+            type_name: std::any::type_name::<L>(),
+            schedule: None,
+            state_drivers: Vec::new(),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+/// Checks that every dependency of `layer` is among `attached`, and that
+/// `layer` itself isn't
+pub(crate) fn validate_dependencies(
+    layer: &AttachedLayer,
+    attached: &[AttachedLayer],
+) -> Result<(), LayerError> {
+    if attached.iter().any(|l| l.type_id == layer.type_id) {
+        return Err(LayerError::AlreadyAttached(layer.type_name));
+    }
+
+    for dependency in layer.layer.dependencies() {
+        if !attached.iter().any(|l| l.type_id == dependency.type_id) {
+            return Err(LayerError::MissingDependency {
+                layer: layer.type_name,
+                dependency: dependency.type_name,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Base;
+    impl Layer for Base {}
+
+    struct Dependent;
+    impl Layer for Dependent {
+        fn dependencies(&self) -> Vec<LayerDependency> {
+            vec![LayerDependency::of::<Base>()]
+        }
+    }
+
+    #[test]
+    fn dependencies_must_be_attached_first() {
+        let dependent = AttachedLayer::new(Dependent);
+        let base = AttachedLayer::new(Base);
+
+        assert!(matches!(
+            validate_dependencies(&dependent, &[]),
+            Err(LayerError::MissingDependency { .. })
+        ));
+        assert!(validate_dependencies(&dependent, &[base]).is_ok());
+    }
+
+    #[test]
+    fn layer_cannot_be_attached_twice() {
+        let attached = [AttachedLayer::new(Base)];
+
+        assert!(matches!(
+            validate_dependencies(&AttachedLayer::new(Base), &attached),
+            Err(LayerError::AlreadyAttached(_))
+        ));
+    }
+}
@@ -1,11 +1,20 @@
 pub mod app;
 pub mod app_builder;
 pub mod app_events;
+pub mod app_state;
+pub mod close_control;
+pub mod console;
 pub mod core_events;
 pub mod debug_stats;
+pub mod engine_config;
+pub mod error_policy;
+pub mod fixed_timestep;
+pub mod frame_pacing;
 pub mod input;
 pub mod layer;
+pub mod profiler;
 pub mod schedule;
+pub mod time;
 
 pub use app::*;
 pub use specs;
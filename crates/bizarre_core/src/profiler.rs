@@ -0,0 +1,322 @@
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, VecDeque},
+    fmt::Write as _,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use specs::{
+    shred::{AccessorCow, RunningTime},
+    System, World,
+};
+
+/// Number of runs each per-system average is taken over
+pub const SYSTEM_HISTORY_LEN: usize = 120;
+
+/// Upper bound of the trace events kept for the Chrome trace, the oldest
+/// ones are dropped first
+pub const MAX_TRACE_EVENTS: usize = 200_000;
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Small sequential id of the current thread, `ThreadId::as_u64` is unstable
+fn current_thread_id() -> u64 {
+    THREAD_ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    pub system: Arc<str>,
+    /// Since the profiler was created
+    pub start: Duration,
+    pub duration: Duration,
+    pub thread_id: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SystemTimeStats {
+    pub last_ms: f64,
+    /// Average over the last [`SYSTEM_HISTORY_LEN`] runs
+    pub average_ms: f64,
+    /// Maximum over the last [`SYSTEM_HISTORY_LEN`] runs
+    pub max_ms: f64,
+}
+
+#[derive(Default)]
+struct SystemHistory {
+    samples: VecDeque<f64>,
+    stats: SystemTimeStats,
+}
+
+impl SystemHistory {
+    fn push(&mut self, ms: f64) {
+        if self.samples.len() == SYSTEM_HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ms);
+
+        self.stats = SystemTimeStats {
+            last_ms: ms,
+            average_ms: self.samples.iter().sum::<f64>() / self.samples.len() as f64,
+            max_ms: self.samples.iter().copied().fold(0.0, f64::max),
+        };
+    }
+}
+
+#[derive(Default)]
+struct ProfilerData {
+    systems: BTreeMap<Arc<str>, SystemHistory>,
+    events: VecDeque<TraceEvent>,
+    thread_names: BTreeMap<u64, String>,
+}
+
+struct ProfilerInner {
+    epoch: Instant,
+    enabled: AtomicBool,
+    tracing: AtomicBool,
+    data: Mutex<ProfilerData>,
+}
+
+/// Collects the run times of the systems wrapped by the
+/// [`crate::schedule::ScheduleBuilder`]. Clones share the same data, and the
+/// app inserts one as a resource. Disabled unless the app is built with
+/// [`crate::app_builder::AppBuilder::with_profiling`] or a Chrome trace path.
+#[derive(Clone)]
+pub struct Profiler(Arc<ProfilerInner>);
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self(Arc::new(ProfilerInner {
+            epoch: Instant::now(),
+            enabled: AtomicBool::new(false),
+            tracing: AtomicBool::new(false),
+            data: Mutex::default(),
+        }))
+    }
+}
+
+impl Profiler {
+    pub fn is_enabled(&self) -> bool {
+        self.0.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.0.tracing.load(Ordering::Relaxed)
+    }
+
+    /// Trace events for the Chrome trace are only kept while tracing
+    pub fn set_tracing(&self, tracing: bool) {
+        self.0.tracing.store(tracing, Ordering::Relaxed);
+    }
+
+    pub fn record(&self, system: &Arc<str>, start: Instant, end: Instant) {
+        let thread_id = current_thread_id();
+        let duration = end - start;
+
+        let mut data = self.0.data.lock().expect("Failed to lock the profiler");
+
+        data.systems
+            .entry(system.clone())
+            .or_default()
+            .push(duration.as_secs_f64() * 1000.0);
+
+        data.thread_names.entry(thread_id).or_insert_with(|| {
+            std::thread::current()
+                .name()
+                .map(String::from)
+                .unwrap_or_else(|| format!("thread {thread_id}"))
+        });
+
+        if !self.is_tracing() {
+            return;
+        }
+
+        if data.events.len() == MAX_TRACE_EVENTS {
+            data.events.pop_front();
+        }
+        data.events.push_back(TraceEvent {
+            system: system.clone(),
+            start: start.saturating_duration_since(self.0.epoch),
+            duration,
+            thread_id,
+        });
+    }
+
+    pub fn system_stats(&self) -> BTreeMap<String, SystemTimeStats> {
+        let data = self.0.data.lock().expect("Failed to lock the profiler");
+        data.systems
+            .iter()
+            .map(|(name, history)| (name.to_string(), history.stats))
+            .collect()
+    }
+
+    pub fn trace_events(&self) -> Vec<TraceEvent> {
+        let data = self.0.data.lock().expect("Failed to lock the profiler");
+        data.events.iter().cloned().collect()
+    }
+
+    pub fn clear_trace(&self) {
+        let mut data = self.0.data.lock().expect("Failed to lock the profiler");
+        data.events.clear();
+    }
+
+    /// Trace in the Chrome trace event format, viewable in `chrome://tracing`
+    /// and Perfetto
+    pub fn to_chrome_trace(&self) -> String {
+        let data = self.0.data.lock().expect("Failed to lock the profiler");
+
+        let mut events = Vec::with_capacity(data.events.len() + data.thread_names.len());
+
+        for (thread_id, name) in &data.thread_names {
+            events.push(format!(
+                r#"{{"name":"thread_name","ph":"M","pid":1,"tid":{thread_id},"args":{{"name":"{}"}}}}"#,
+                escape_json(name)
+            ));
+        }
+
+        for event in &data.events {
+            events.push(format!(
+                r#"{{"name":"{}","cat":"system","ph":"X","pid":1,"tid":{},"ts":{:.3},"dur":{:.3}}}"#,
+                escape_json(&event.system),
+                event.thread_id,
+                event.start.as_secs_f64() * 1_000_000.0,
+                event.duration.as_secs_f64() * 1_000_000.0,
+            ));
+        }
+
+        format!(
+            "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n{}\n]}}\n",
+            events.join(",\n")
+        )
+    }
+
+    pub fn write_chrome_trace<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_chrome_trace())
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Records every run of the wrapped system in the profiler
+pub struct ProfiledSystem<S> {
+    pub inner: S,
+    name: Arc<str>,
+    profiler: Profiler,
+}
+
+impl<S> ProfiledSystem<S> {
+    pub fn new(inner: S, name: &str, profiler: Profiler) -> Self {
+        Self {
+            inner,
+            name: name.into(),
+            profiler,
+        }
+    }
+}
+
+impl<'a, S> System<'a> for ProfiledSystem<S>
+where
+    S: System<'a>,
+{
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        if !self.profiler.is_enabled() {
+            self.inner.run(data);
+            return;
+        }
+
+        let start = Instant::now();
+        self.inner.run(data);
+        self.profiler.record(&self.name, start, Instant::now());
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.inner.running_time()
+    }
+
+    fn accessor<'b>(&'b self) -> AccessorCow<'a, 'b, Self> {
+        match self.inner.accessor() {
+            AccessorCow::Ref(accessor) => AccessorCow::Ref(accessor),
+            AccessorCow::Owned(accessor) => AccessorCow::Owned(accessor),
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        self.inner.setup(world);
+    }
+
+    fn dispose(self, world: &mut World)
+    where
+        Self: Sized,
+    {
+        self.inner.dispose(world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn averages_system_times() {
+        let profiler = Profiler::default();
+        let name: Arc<str> = "system".into();
+
+        let start = Instant::now();
+        profiler.record(&name, start, start + Duration::from_millis(2));
+        profiler.record(&name, start, start + Duration::from_millis(4));
+
+        let stats = profiler.system_stats()["system"];
+        assert_eq!(stats.last_ms, 4.0);
+        assert_eq!(stats.average_ms, 3.0);
+        assert_eq!(stats.max_ms, 4.0);
+    }
+
+    #[test]
+    fn exports_chrome_trace() {
+        let profiler = Profiler::default();
+        let start = Instant::now();
+        profiler.record(&"untraced".into(), start, start);
+        profiler.set_tracing(true);
+        profiler.record(&"a \"quoted\" system".into(), start, start);
+
+        let trace = profiler.to_chrome_trace();
+        assert!(trace.contains(r#""name":"a \"quoted\" system","cat":"system","ph":"X""#));
+        assert!(trace.contains(r#""ph":"M""#));
+        assert_eq!(profiler.trace_events().len(), 1);
+    }
+}
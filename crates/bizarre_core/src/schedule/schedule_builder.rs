@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+
+use bizarre_common::system_error::{FallibleSystem, TrySystem};
+use specs::{shred::Accessor, Dispatcher, DispatcherBuilder, System};
+
+use crate::profiler::{ProfiledSystem, Profiler};
+
+use super::{
+    schedule_error::ScheduleError,
+    schedule_info::{
+        short_type_name, AccessKind, DispatcherInfo, ResourceAccess, ScheduleInfo, SystemGroup,
+        SystemInfo,
+    },
+    system_config::SystemConfig,
+    system_graph::{order_systems, GraphNode, OrderedSystem},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScheduleType {
+    Frame,
+    Setup,
+    /// Runs zero or more times per frame with a fixed delta time
+    FixedUpdate,
+    /// Runs once when the app is destroyed, before the layers are detached
+    Shutdown,
+}
+
+pub struct Schedule {
+    pub frame_dispatcher: Dispatcher<'static, 'static>,
+    pub setup_dispatcher: Dispatcher<'static, 'static>,
+    pub fixed_dispatcher: Dispatcher<'static, 'static>,
+    pub shutdown_dispatcher: Dispatcher<'static, 'static>,
+    pub info: ScheduleInfo,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            frame_dispatcher: DispatcherBuilder::new().build(),
+            setup_dispatcher: DispatcherBuilder::new().build(),
+            fixed_dispatcher: DispatcherBuilder::new().build(),
+            shutdown_dispatcher: DispatcherBuilder::new().build(),
+            info: ScheduleInfo::default(),
+        }
+    }
+}
+
+type AddSystemFn = Box<dyn FnOnce(&mut DispatcherBuilder<'static, 'static>, &str, &[&str])>;
+
+/// Systems are only handed to specs once the whole schedule is known, so the
+/// ordering can be checked beforehand
+struct PendingSystem {
+    config: SystemConfig,
+    epoch: usize,
+    data: String,
+    accesses: Vec<ResourceAccess>,
+    add: AddSystemFn,
+}
+
+#[derive(Default)]
+struct PendingSchedule {
+    systems: Vec<PendingSystem>,
+    barriers: usize,
+    /// Labels of the dispatchers that run before this one
+    preceding: HashSet<String>,
+}
+
+impl PendingSchedule {
+    fn order(&self) -> Result<Vec<Vec<OrderedSystem>>, ScheduleError> {
+        let nodes = self
+            .systems
+            .iter()
+            .map(|s| GraphNode {
+                config: &s.config,
+                epoch: s.epoch,
+            })
+            .collect::<Vec<_>>();
+
+        order_systems(&nodes, &self.preceding)
+    }
+
+    fn info(&self, groups: &[Vec<OrderedSystem>]) -> DispatcherInfo {
+        let groups = groups
+            .iter()
+            .map(|group| SystemGroup {
+                stage: self.systems[group[0].index].config.stage,
+                systems: group
+                    .iter()
+                    .map(|system| {
+                        let pending = &self.systems[system.index];
+                        SystemInfo {
+                            name: pending.config.name.clone(),
+                            stage: pending.config.stage,
+                            sets: pending.config.sets.clone(),
+                            data: pending.data.clone(),
+                            dependencies: system
+                                .dependencies
+                                .iter()
+                                .map(|d| self.systems[*d].config.name.clone())
+                                .collect(),
+                            accesses: pending.accesses.clone(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        DispatcherInfo { groups }
+    }
+
+    fn build(self) -> Result<(Dispatcher<'static, 'static>, DispatcherInfo), ScheduleError> {
+        let groups = self.order()?;
+        let info = self.info(&groups);
+
+        let mut systems = self
+            .systems
+            .into_iter()
+            .map(|s| (s.config.name, Some(s.add)))
+            .collect::<Vec<_>>();
+
+        let mut builder = DispatcherBuilder::new();
+
+        for (i, group) in groups.into_iter().enumerate() {
+            if i > 0 {
+                builder.add_barrier();
+            }
+
+            for system in group {
+                let dependencies = system
+                    .dependencies
+                    .iter()
+                    .map(|d| systems[*d].0.clone())
+                    .collect::<Vec<_>>();
+                let dependencies = dependencies.iter().map(String::as_str).collect::<Vec<_>>();
+
+                let (name, add) = &mut systems[system.index];
+                let add = add.take().unwrap();
+                add(&mut builder, name, &dependencies);
+            }
+        }
+
+        Ok((builder.build(), info))
+    }
+}
+
+#[derive(Default)]
+pub struct ScheduleBuilder {
+    frame: PendingSchedule,
+    setup: PendingSchedule,
+    fixed: PendingSchedule,
+    shutdown: PendingSchedule,
+    profiler: Profiler,
+}
+
+impl ScheduleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Systems added to the schedule record their run times in `profiler`
+    pub fn with_profiler(profiler: Profiler) -> Self {
+        Self {
+            profiler,
+            ..Default::default()
+        }
+    }
+
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    fn pending_mut(&mut self, schedule_type: ScheduleType) -> &mut PendingSchedule {
+        match schedule_type {
+            ScheduleType::Frame => &mut self.frame,
+            ScheduleType::Setup => &mut self.setup,
+            ScheduleType::FixedUpdate => &mut self.fixed,
+            ScheduleType::Shutdown => &mut self.shutdown,
+        }
+    }
+
+    #[deprecated]
+    pub fn with_frame_system<F>(
+        &mut self,
+        system: F,
+        name: &str,
+        dependencies: &[&str],
+    ) -> &mut Self
+    where
+        F: for<'a> System<'a> + 'static + Send,
+    {
+        self.with_system(ScheduleType::Frame, system, name, dependencies)
+    }
+
+    /// Adds the system to the [`super::Stage::Update`] stage, running after its
+    /// dependencies
+    pub fn with_system<F>(
+        &mut self,
+        schedule_type: ScheduleType,
+        system: F,
+        name: &str,
+        dependencies: &[&str],
+    ) -> &mut Self
+    where
+        F: for<'a> System<'a> + 'static + Send,
+    {
+        let config = dependencies
+            .iter()
+            .fold(SystemConfig::new(name), |config, dep| config.after(dep));
+
+        self.with_configured_system(schedule_type, system, config)
+    }
+
+    pub fn with_configured_system<F>(
+        &mut self,
+        schedule_type: ScheduleType,
+        system: F,
+        config: SystemConfig,
+    ) -> &mut Self
+    where
+        F: for<'a> System<'a> + 'static + Send,
+    {
+        let data = short_type_name(std::any::type_name::<<F as System<'static>>::SystemData>());
+        let accesses = accesses_of(&system);
+        let system = ProfiledSystem::new(system, &config.name, self.profiler.clone());
+
+        let pending = self.pending_mut(schedule_type);
+        pending.systems.push(PendingSystem {
+            config,
+            epoch: pending.barriers,
+            data,
+            accesses,
+            add: Box::new(move |builder, name, dependencies| {
+                builder.add(system, name, dependencies);
+            }),
+        });
+        self
+    }
+
+    /// Adds a system whose errors are handled by the error policy of the app
+    pub fn with_try_system<F>(
+        &mut self,
+        schedule_type: ScheduleType,
+        system: F,
+        config: SystemConfig,
+    ) -> &mut Self
+    where
+        F: for<'a> TrySystem<'a> + 'static + Send,
+    {
+        let system = FallibleSystem::new(system, &config.name);
+        self.with_configured_system(schedule_type, system, config)
+    }
+
+    /// The systems can be ordered after the systems and sets of `info`,
+    /// a schedule dispatched before this one. Shutdown dispatchers run in
+    /// the reverse order, so their labels are left out.
+    pub fn with_preceding_schedule(&mut self, info: &ScheduleInfo) -> &mut Self {
+        for schedule_type in [
+            ScheduleType::Frame,
+            ScheduleType::Setup,
+            ScheduleType::FixedUpdate,
+        ] {
+            let labels = info.labels(schedule_type).map(String::from);
+            self.pending_mut(schedule_type).preceding.extend(labels);
+        }
+        self
+    }
+
+    /// Systems added after the barrier run after every system of the same
+    /// stage added before it
+    pub fn add_barrier(&mut self, schedule_type: ScheduleType) -> &mut Self {
+        self.pending_mut(schedule_type).barriers += 1;
+        self
+    }
+
+    /// Execution order of the systems added so far
+    pub fn info(&self) -> Result<ScheduleInfo, ScheduleError> {
+        let info = |pending: &PendingSchedule| -> Result<DispatcherInfo, ScheduleError> {
+            Ok(pending.info(&pending.order()?))
+        };
+
+        Ok(ScheduleInfo {
+            frame: info(&self.frame)?,
+            setup: info(&self.setup)?,
+            fixed: info(&self.fixed)?,
+            shutdown: info(&self.shutdown)?,
+        })
+    }
+
+    pub fn build(&mut self) -> Result<Schedule, ScheduleError> {
+        let (frame_dispatcher, frame) = std::mem::take(&mut self.frame).build()?;
+        let (setup_dispatcher, setup) = std::mem::take(&mut self.setup).build()?;
+        let (fixed_dispatcher, fixed) = std::mem::take(&mut self.fixed).build()?;
+        let (shutdown_dispatcher, shutdown) = std::mem::take(&mut self.shutdown).build()?;
+
+        Ok(Schedule {
+            frame_dispatcher,
+            setup_dispatcher,
+            fixed_dispatcher,
+            shutdown_dispatcher,
+            info: ScheduleInfo {
+                frame,
+                setup,
+                fixed,
+                shutdown,
+            },
+        })
+    }
+}
+
+fn accesses_of<F>(system: &F) -> Vec<ResourceAccess>
+where
+    F: for<'a> System<'a>,
+{
+    let accessor = <F as System<'static>>::accessor(system);
+    let access = |kind| move |id| ResourceAccess { id, kind };
+
+    accessor
+        .reads()
+        .into_iter()
+        .map(access(AccessKind::Read))
+        .chain(accessor.writes().into_iter().map(access(AccessKind::Write)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{shred::ResourceId, System, World, WorldExt, Write};
+
+    use super::*;
+    use crate::schedule::Stage;
+
+    #[derive(Default)]
+    struct RunOrder(Vec<&'static str>);
+
+    struct Named(&'static str);
+
+    impl<'a> System<'a> for Named {
+        type SystemData = Write<'a, RunOrder>;
+
+        fn run(&mut self, mut order: Self::SystemData) {
+            order.0.push(self.0);
+        }
+    }
+
+    #[test]
+    fn runs_stages_in_order() {
+        let mut builder = ScheduleBuilder::new();
+        builder
+            .with_configured_system(
+                ScheduleType::Frame,
+                Named("render"),
+                SystemConfig::new("render").stage(Stage::Render),
+            )
+            .with_system(ScheduleType::Frame, Named("update"), "update", &[])
+            .with_configured_system(
+                ScheduleType::Frame,
+                Named("first"),
+                SystemConfig::new("first").stage(Stage::First),
+            );
+
+        let mut schedule = builder.build().unwrap();
+        let mut world = World::new();
+        schedule.frame_dispatcher.setup(&mut world);
+        schedule.frame_dispatcher.dispatch(&world);
+
+        assert_eq!(
+            world.read_resource::<RunOrder>().0,
+            ["first", "update", "render"]
+        );
+
+        let info = &schedule.info.frame;
+        assert_eq!(info.barrier_count(), 2);
+        assert_eq!(
+            info.systems().next().unwrap().writes().collect::<Vec<_>>(),
+            [&ResourceId::new::<RunOrder>()]
+        );
+    }
+
+    #[test]
+    fn missing_dependency_is_an_error() {
+        let mut builder = ScheduleBuilder::new();
+        builder.with_system(ScheduleType::Frame, Named("a"), "a", &["b"]);
+
+        assert!(matches!(
+            builder.build(),
+            Err(ScheduleError::MissingLabel { .. })
+        ));
+    }
+}
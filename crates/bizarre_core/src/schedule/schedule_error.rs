@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ScheduleError {
+    #[error("system \"{0}\" is added more than once")]
+    DuplicateSystem(String),
+
+    #[error(
+        "system \"{system}\" is ordered against \"{label}\", which is neither a system nor a set"
+    )]
+    MissingLabel { system: String, label: String },
+
+    #[error("systems form a dependency cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+
+    #[error("system \"{system}\" has to run after \"{dependency}\", but is in an earlier stage or before a barrier")]
+    OrderConflict { system: String, dependency: String },
+
+    #[error("system \"{system}\" has to run before \"{label}\", which is in a schedule dispatched earlier")]
+    BeforePrecedingSchedule { system: String, label: String },
+}
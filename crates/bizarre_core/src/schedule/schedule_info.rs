@@ -0,0 +1,350 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    path::Path,
+};
+
+use specs::shred::ResourceId;
+
+use super::{schedule_builder::ScheduleType, system_config::Stage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// Resource or component storage a system fetches, as reported by its
+/// `System::accessor`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceAccess {
+    pub id: ResourceId,
+    pub kind: AccessKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemInfo {
+    pub name: String,
+    pub stage: Stage,
+    pub sets: Vec<String>,
+    /// `SystemData` type name without the module paths
+    pub data: String,
+    /// Systems of the same group this one runs after
+    pub dependencies: Vec<String>,
+    pub accesses: Vec<ResourceAccess>,
+}
+
+impl SystemInfo {
+    pub fn reads(&self) -> impl Iterator<Item = &ResourceId> {
+        self.accesses_of(AccessKind::Read)
+    }
+
+    pub fn writes(&self) -> impl Iterator<Item = &ResourceId> {
+        self.accesses_of(AccessKind::Write)
+    }
+
+    fn accesses_of(&self, kind: AccessKind) -> impl Iterator<Item = &ResourceId> {
+        self.accesses
+            .iter()
+            .filter(move |a| a.kind == kind)
+            .map(|a| &a.id)
+    }
+}
+
+/// Systems separated from the previous group by a barrier
+#[derive(Debug, Clone)]
+pub struct SystemGroup {
+    pub stage: Stage,
+    pub systems: Vec<SystemInfo>,
+}
+
+/// Two systems writing the same resource without an order between them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteConflict {
+    pub resource: ResourceId,
+    pub systems: [String; 2],
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DispatcherInfo {
+    /// In execution order, with a barrier between each of them
+    pub groups: Vec<SystemGroup>,
+}
+
+impl DispatcherInfo {
+    pub fn systems(&self) -> impl Iterator<Item = &SystemInfo> {
+        self.groups.iter().flat_map(|g| g.systems.iter())
+    }
+
+    pub fn barrier_count(&self) -> usize {
+        self.groups.len().saturating_sub(1)
+    }
+
+    pub fn write_conflicts(&self) -> Vec<WriteConflict> {
+        let mut conflicts = Vec::new();
+
+        for group in &self.groups {
+            let dependencies = group
+                .systems
+                .iter()
+                .map(|s| (s.name.as_str(), &s.dependencies))
+                .collect::<HashMap<_, _>>();
+
+            let ancestors = group
+                .systems
+                .iter()
+                .map(|s| ancestors(&s.name, &dependencies))
+                .collect::<Vec<_>>();
+
+            for (i, a) in group.systems.iter().enumerate() {
+                for (j, b) in group.systems.iter().enumerate().skip(i + 1) {
+                    if ancestors[i].contains(b.name.as_str())
+                        || ancestors[j].contains(a.name.as_str())
+                    {
+                        continue;
+                    }
+
+                    for resource in a.writes().filter(|w| b.writes().any(|o| o == *w)) {
+                        conflicts.push(WriteConflict {
+                            resource: resource.clone(),
+                            systems: [a.name.clone(), b.name.clone()],
+                        });
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+}
+
+fn ancestors<'a>(
+    system: &str,
+    dependencies: &HashMap<&'a str, &'a Vec<String>>,
+) -> HashSet<&'a str> {
+    let mut visited = HashSet::new();
+    let mut stack = dependencies
+        .get(system)
+        .map(|d| d.iter().map(String::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    while let Some(current) = stack.pop() {
+        if visited.insert(current) {
+            if let Some(deps) = dependencies.get(current) {
+                stack.extend(deps.iter().map(String::as_str));
+            }
+        }
+    }
+
+    visited
+}
+
+/// Systems of every dispatcher of a [`super::Schedule`]
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleInfo {
+    pub frame: DispatcherInfo,
+    pub setup: DispatcherInfo,
+    pub fixed: DispatcherInfo,
+    pub shutdown: DispatcherInfo,
+}
+
+impl ScheduleInfo {
+    /// Names and sets of the systems of a dispatcher
+    pub fn labels(&self, schedule_type: ScheduleType) -> impl Iterator<Item = &str> {
+        self.get(schedule_type)
+            .groups
+            .iter()
+            .flat_map(|group| &group.systems)
+            .flat_map(|system| {
+                std::iter::once(system.name.as_str()).chain(system.sets.iter().map(String::as_str))
+            })
+    }
+
+    pub fn get(&self, schedule_type: ScheduleType) -> &DispatcherInfo {
+        match schedule_type {
+            ScheduleType::Frame => &self.frame,
+            ScheduleType::Setup => &self.setup,
+            ScheduleType::FixedUpdate => &self.fixed,
+            ScheduleType::Shutdown => &self.shutdown,
+        }
+    }
+
+    fn dispatchers(&self) -> [(ScheduleType, &DispatcherInfo); 4] {
+        [
+            (ScheduleType::Setup, &self.setup),
+            (ScheduleType::FixedUpdate, &self.fixed),
+            (ScheduleType::Frame, &self.frame),
+            (ScheduleType::Shutdown, &self.shutdown),
+        ]
+    }
+
+    pub fn write_conflicts(&self) -> Vec<(ScheduleType, WriteConflict)> {
+        self.dispatchers()
+            .into_iter()
+            .flat_map(|(schedule_type, info)| {
+                info.write_conflicts()
+                    .into_iter()
+                    .map(move |c| (schedule_type, c))
+            })
+            .collect()
+    }
+
+    /// Graphviz graph with a cluster per dispatcher and stage. Solid edges are
+    /// dependencies, dashed ones go through barriers and red ones connect
+    /// systems with a write conflict.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        let _ = writeln!(dot, "digraph schedule {{");
+        let _ = writeln!(dot, "    rankdir=LR;");
+        let _ = writeln!(dot, "    node [shape=box, fontname=monospace];");
+
+        for (schedule_type, info) in self.dispatchers() {
+            let prefix = format!("{schedule_type:?}");
+            let id = |system: &str| format!("\"{prefix}/{system}\"");
+
+            let _ = writeln!(dot, "    subgraph \"cluster_{prefix}\" {{");
+            let _ = writeln!(dot, "        label=\"{prefix}\";");
+
+            for (i, group) in info.groups.iter().enumerate() {
+                let _ = writeln!(dot, "        subgraph \"cluster_{prefix}_{i}\" {{");
+                let _ = writeln!(dot, "            label=\"{:?}\";", group.stage);
+
+                for system in &group.systems {
+                    let label = format!("{}\\n{}", system.name, system.data).replace('"', "\\\"");
+
+                    let _ = writeln!(dot, "            {} [label=\"{label}\"];", id(&system.name));
+                }
+
+                let _ = writeln!(dot, "        }}");
+
+                for system in &group.systems {
+                    for dependency in &system.dependencies {
+                        let _ =
+                            writeln!(dot, "        {} -> {};", id(dependency), id(&system.name));
+                    }
+                }
+
+                if i > 0 {
+                    let barrier = id(&format!("barrier {i}"));
+                    let _ = writeln!(dot, "        {barrier} [shape=point];");
+                    for system in &info.groups[i - 1].systems {
+                        let _ = writeln!(
+                            dot,
+                            "        {} -> {barrier} [style=dashed];",
+                            id(&system.name)
+                        );
+                    }
+                    for system in &group.systems {
+                        let _ = writeln!(
+                            dot,
+                            "        {barrier} -> {} [style=dashed];",
+                            id(&system.name)
+                        );
+                    }
+                }
+            }
+
+            for conflict in info.write_conflicts() {
+                let [a, b] = &conflict.systems;
+                let _ = writeln!(dot, "        {} -> {} [color=red, dir=none];", id(a), id(b));
+            }
+
+            let _ = writeln!(dot, "    }}");
+        }
+
+        let _ = writeln!(dot, "}}");
+
+        dot
+    }
+
+    pub fn write_dot<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_dot())
+    }
+}
+
+/// Drops the module paths, `a::B<c::D>` becomes `B<D>`
+pub(crate) fn short_type_name(type_name: &str) -> String {
+    let mut short = String::with_capacity(type_name.len());
+    let mut segment_start = 0;
+
+    for (i, c) in type_name.char_indices() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            continue;
+        }
+        short.push_str(last_segment(&type_name[segment_start..i]));
+        short.push(c);
+        segment_start = i + c.len_utf8();
+    }
+    short.push_str(last_segment(&type_name[segment_start..]));
+
+    short
+}
+
+fn last_segment(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Transform;
+    struct Camera;
+
+    fn system(name: &str, dependencies: &[&str], writes: &[ResourceId]) -> SystemInfo {
+        SystemInfo {
+            name: name.into(),
+            stage: Stage::Update,
+            sets: Vec::new(),
+            data: String::new(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            accesses: writes
+                .iter()
+                .map(|id| ResourceAccess {
+                    id: id.clone(),
+                    kind: AccessKind::Write,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn shortens_type_names() {
+        assert_eq!(
+            short_type_name(
+                "(shred::world::data::Read<'_, specs::world::entity::EntitiesRes>, \
+                 shred::world::data::Write<'_, shrev::EventChannel<a::Event>>)"
+            ),
+            "(Read<'_, EntitiesRes>, Write<'_, EventChannel<Event>>)"
+        );
+    }
+
+    #[test]
+    fn finds_unordered_writes() {
+        let info = DispatcherInfo {
+            groups: vec![SystemGroup {
+                stage: Stage::Update,
+                systems: vec![
+                    system("a", &[], &[ResourceId::new::<Transform>()]),
+                    system("b", &["a"], &[ResourceId::new::<Transform>()]),
+                    system(
+                        "c",
+                        &[],
+                        &[ResourceId::new::<Transform>(), ResourceId::new::<Camera>()],
+                    ),
+                ],
+            }],
+        };
+
+        let conflicts = info.write_conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.iter().all(|c| c.systems[1] == "c"));
+
+        let schedule = ScheduleInfo {
+            frame: info,
+            ..Default::default()
+        };
+        assert!(schedule.to_dot().contains("color=red"));
+    }
+}
@@ -0,0 +1,54 @@
+/// Stages run one after another, with a barrier between each of them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    First,
+    PreUpdate,
+    #[default]
+    Update,
+    PostUpdate,
+    Render,
+    Last,
+}
+
+/// Placement of a system in a schedule. Labels given to `after` and `before`
+/// name either a system or a set.
+#[derive(Debug, Clone)]
+pub struct SystemConfig {
+    pub name: String,
+    pub stage: Stage,
+    pub after: Vec<String>,
+    pub before: Vec<String>,
+    pub sets: Vec<String>,
+}
+
+impl SystemConfig {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            stage: Stage::default(),
+            after: Vec::new(),
+            before: Vec::new(),
+            sets: Vec::new(),
+        }
+    }
+
+    pub fn stage(mut self, stage: Stage) -> Self {
+        self.stage = stage;
+        self
+    }
+
+    pub fn after(mut self, label: &str) -> Self {
+        self.after.push(label.into());
+        self
+    }
+
+    pub fn before(mut self, label: &str) -> Self {
+        self.before.push(label.into());
+        self
+    }
+
+    pub fn in_set(mut self, set: &str) -> Self {
+        self.sets.push(set.into());
+        self
+    }
+}
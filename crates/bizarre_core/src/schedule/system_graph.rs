@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, HashSet};
+
+use super::{schedule_error::ScheduleError, system_config::SystemConfig, Stage};
+
+pub(crate) struct GraphNode<'a> {
+    pub config: &'a SystemConfig,
+    /// Number of barriers added to the schedule before the system
+    pub epoch: usize,
+}
+
+impl GraphNode<'_> {
+    fn group(&self) -> (Stage, usize) {
+        (self.config.stage, self.epoch)
+    }
+}
+
+pub(crate) struct OrderedSystem {
+    pub index: usize,
+    /// Systems of the same group this one has to run after
+    pub dependencies: Vec<usize>,
+}
+
+/// Splits the systems into groups separated by barriers and sorts each group
+/// so that every system comes after its dependencies. Systems keep the order
+/// they were added in unless a constraint says otherwise.
+///
+/// `preceding` holds the labels of the schedules dispatched before this one.
+/// Running after them is a given, running before them is an error.
+pub(crate) fn order_systems(
+    nodes: &[GraphNode],
+    preceding: &HashSet<String>,
+) -> Result<Vec<Vec<OrderedSystem>>, ScheduleError> {
+    let mut names = HashSet::new();
+    for node in nodes {
+        if !names.insert(node.config.name.as_str()) {
+            return Err(ScheduleError::DuplicateSystem(node.config.name.clone()));
+        }
+    }
+
+    let resolve = |system: &str, label: &str| -> Result<Vec<usize>, ScheduleError> {
+        let matches = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.config.name == label || n.config.sets.iter().any(|s| s == label))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+
+        if matches.is_empty() && !preceding.contains(label) {
+            Err(ScheduleError::MissingLabel {
+                system: system.into(),
+                label: label.into(),
+            })
+        } else {
+            Ok(matches)
+        }
+    };
+
+    let mut predecessors = vec![Vec::<usize>::new(); nodes.len()];
+
+    for (index, node) in nodes.iter().enumerate() {
+        let name = node.config.name.as_str();
+        let mut edges = Vec::new();
+
+        for label in &node.config.after {
+            edges.extend(resolve(name, label)?.into_iter().map(|dep| (dep, index)));
+        }
+        for label in &node.config.before {
+            let next = resolve(name, label)?;
+            if next.is_empty() {
+                return Err(ScheduleError::BeforePrecedingSchedule {
+                    system: name.into(),
+                    label: label.clone(),
+                });
+            }
+            edges.extend(next.into_iter().map(|next| (index, next)));
+        }
+
+        // Ordering a system against its own set is allowed and means nothing
+        for (from, to) in edges.into_iter().filter(|(from, to)| from != to) {
+            match nodes[from].group().cmp(&nodes[to].group()) {
+                std::cmp::Ordering::Less => {}
+                std::cmp::Ordering::Greater => {
+                    return Err(ScheduleError::OrderConflict {
+                        system: nodes[to].config.name.clone(),
+                        dependency: nodes[from].config.name.clone(),
+                    })
+                }
+                std::cmp::Ordering::Equal => {
+                    if !predecessors[to].contains(&from) {
+                        predecessors[to].push(from);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups = BTreeMap::<(Stage, usize), Vec<usize>>::new();
+    for (index, node) in nodes.iter().enumerate() {
+        groups.entry(node.group()).or_default().push(index);
+    }
+
+    groups
+        .into_values()
+        .map(|group| sort_group(nodes, &group, &predecessors))
+        .collect()
+}
+
+fn sort_group(
+    nodes: &[GraphNode],
+    group: &[usize],
+    predecessors: &[Vec<usize>],
+) -> Result<Vec<OrderedSystem>, ScheduleError> {
+    let mut remaining = group.to_vec();
+    let mut sorted = Vec::with_capacity(group.len());
+
+    while !remaining.is_empty() {
+        let ready = remaining
+            .iter()
+            .position(|i| predecessors[*i].iter().all(|p| !remaining.contains(p)));
+
+        match ready {
+            Some(position) => {
+                let index = remaining.remove(position);
+                sorted.push(OrderedSystem {
+                    index,
+                    dependencies: predecessors[index].clone(),
+                });
+            }
+            None => return Err(find_cycle(nodes, &remaining, predecessors)),
+        }
+    }
+
+    Ok(sorted)
+}
+
+/// Every remaining system has a remaining predecessor, so walking them
+/// backwards has to come back to an already visited one
+fn find_cycle(
+    nodes: &[GraphNode],
+    remaining: &[usize],
+    predecessors: &[Vec<usize>],
+) -> ScheduleError {
+    let mut path = vec![remaining[0]];
+
+    loop {
+        let current = *path.last().unwrap();
+        let previous = *predecessors[current]
+            .iter()
+            .find(|p| remaining.contains(p))
+            .unwrap();
+
+        if let Some(start) = path.iter().position(|i| *i == previous) {
+            let mut cycle = path[start..]
+                .iter()
+                .rev()
+                .map(|i| nodes[*i].config.name.clone())
+                .collect::<Vec<_>>();
+            cycle.push(cycle[0].clone());
+            return ScheduleError::Cycle(cycle);
+        }
+
+        path.push(previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(configs: &[SystemConfig]) -> Result<Vec<Vec<&str>>, ScheduleError> {
+        let nodes = configs
+            .iter()
+            .map(|config| GraphNode { config, epoch: 0 })
+            .collect::<Vec<_>>();
+
+        order_systems(&nodes, &HashSet::new()).map(|groups| {
+            groups
+                .iter()
+                .map(|g| g.iter().map(|s| configs[s.index].name.as_str()).collect())
+                .collect()
+        })
+    }
+
+    #[test]
+    fn sorts_by_stage_and_labels() {
+        let configs = [
+            SystemConfig::new("render").stage(Stage::Render),
+            SystemConfig::new("b").after("physics"),
+            SystemConfig::new("a").in_set("physics"),
+            SystemConfig::new("input").stage(Stage::First),
+            SystemConfig::new("c").before("a"),
+        ];
+
+        // "b" waits for the "physics" set, which waits for "c"
+        assert_eq!(
+            order(&configs).unwrap(),
+            vec![vec!["input"], vec!["c", "a", "b"], vec!["render"]]
+        );
+    }
+
+    #[test]
+    fn labels_of_preceding_schedules_are_satisfied() {
+        let configs = [
+            SystemConfig::new("a").after("app_system"),
+            SystemConfig::new("b").before("app_system"),
+        ];
+        let nodes = configs
+            .iter()
+            .map(|config| GraphNode { config, epoch: 0 })
+            .collect::<Vec<_>>();
+        let preceding = HashSet::from(["app_system".to_string()]);
+
+        assert!(order_systems(&nodes[..1], &preceding).is_ok());
+        assert_eq!(
+            order_systems(&nodes, &preceding).err(),
+            Some(ScheduleError::BeforePrecedingSchedule {
+                system: "b".into(),
+                label: "app_system".into()
+            })
+        );
+    }
+
+    #[test]
+    fn reports_missing_labels_and_cycles() {
+        assert_eq!(
+            order(&[SystemConfig::new("a").after("nothing")]).unwrap_err(),
+            ScheduleError::MissingLabel {
+                system: "a".into(),
+                label: "nothing".into()
+            }
+        );
+
+        let cycle = [
+            SystemConfig::new("a").after("c"),
+            SystemConfig::new("b").after("a"),
+            SystemConfig::new("c").after("b"),
+        ];
+        assert!(matches!(order(&cycle), Err(ScheduleError::Cycle(c)) if c.len() == 4));
+
+        let conflict = [
+            SystemConfig::new("a").stage(Stage::First).after("b"),
+            SystemConfig::new("b"),
+        ];
+        assert_eq!(
+            order(&conflict).unwrap_err(),
+            ScheduleError::OrderConflict {
+                system: "a".into(),
+                dependency: "b".into()
+            }
+        );
+    }
+}
@@ -0,0 +1,438 @@
+use std::time::Duration;
+
+use anyhow::bail;
+use specs::{
+    shrev::EventChannel, Component, DenseVecStorage, Entities, Entity, Join, Read, System,
+    WorldExt, Write, WriteStorage,
+};
+
+use crate::console::Console;
+
+/// Game clock of the app. The scaled delta is the real frame time multiplied
+/// by the time scale, and zero while paused. `DeltaTime` and `RunningTime`
+/// follow the scaled clock, the fixed updates too.
+#[derive(Debug, Clone)]
+pub struct Time {
+    scale: f64,
+    paused: bool,
+    pending_steps: u32,
+    delta: Duration,
+    real_delta: Duration,
+    elapsed: Duration,
+    real_elapsed: Duration,
+    frame: u64,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            paused: false,
+            pending_steps: 0,
+            delta: Duration::ZERO,
+            real_delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            real_elapsed: Duration::ZERO,
+            frame: 0,
+        }
+    }
+}
+
+impl Time {
+    /// Scaled time since the previous frame
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_secs(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Measured time since the previous frame, whatever the scale and pause
+    pub fn real_delta(&self) -> Duration {
+        self.real_delta
+    }
+
+    /// Sum of the scaled deltas
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn real_elapsed(&self) -> Duration {
+        self.real_elapsed
+    }
+
+    /// Frames run so far, counted while paused too
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Panics if the scale is negative or not finite
+    pub fn set_scale(&mut self, scale: f64) {
+        assert!(
+            scale.is_finite() && scale >= 0.0,
+            "Time scale must be a finite non-negative number"
+        );
+        self.scale = scale;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.pending_steps = 0;
+    }
+
+    pub fn pause(&mut self) {
+        self.set_paused(true);
+    }
+
+    pub fn resume(&mut self) {
+        self.set_paused(false);
+    }
+
+    /// While paused, lets the next `frames` frames advance as if unpaused
+    pub fn step(&mut self, frames: u32) {
+        if self.paused {
+            self.pending_steps = self.pending_steps.saturating_add(frames);
+        }
+    }
+
+    pub(crate) fn advance(&mut self, real_delta: Duration) {
+        let running = match (self.paused, self.pending_steps) {
+            (false, _) => true,
+            (true, 0) => false,
+            (true, _) => {
+                self.pending_steps -= 1;
+                true
+            }
+        };
+
+        self.real_delta = real_delta;
+        self.real_elapsed += real_delta;
+        self.delta = if running {
+            real_delta.mul_f64(self.scale)
+        } else {
+            Duration::ZERO
+        };
+        self.elapsed += self.delta;
+        self.frame += 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    /// Finishes once and stays finished until reset
+    Once,
+    /// Starts over when finished, keeping the overshoot
+    Repeating,
+}
+
+/// Counts the game time down, ticked by the [`TimerSystem`] which sends a
+/// [`TimerFinished`] event each time it finishes
+#[derive(Debug, Clone)]
+pub struct Timer {
+    duration: Duration,
+    elapsed: Duration,
+    mode: TimerMode,
+    paused: bool,
+    /// Ticked with the real delta, so it runs while the game is paused
+    pub unscaled: bool,
+    times_finished: u32,
+}
+
+impl Component for Timer {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Timer {
+    /// Panics if `duration` is zero
+    pub fn new(duration: Duration, mode: TimerMode) -> Self {
+        assert!(!duration.is_zero(), "Timer duration must not be zero");
+
+        Self {
+            duration,
+            elapsed: Duration::ZERO,
+            mode,
+            paused: false,
+            unscaled: false,
+            times_finished: 0,
+        }
+    }
+
+    pub fn once(duration: Duration) -> Self {
+        Self::new(duration, TimerMode::Once)
+    }
+
+    pub fn repeating(duration: Duration) -> Self {
+        Self::new(duration, TimerMode::Repeating)
+    }
+
+    pub fn with_unscaled(mut self, unscaled: bool) -> Self {
+        self.unscaled = unscaled;
+        self
+    }
+
+    /// Advances the timer and returns how many times it finished during
+    /// the tick
+    pub fn tick(&mut self, delta: Duration) -> u32 {
+        self.times_finished = 0;
+
+        if self.paused || self.is_finished() {
+            return 0;
+        }
+
+        self.elapsed += delta;
+
+        match self.mode {
+            TimerMode::Once => {
+                if self.elapsed >= self.duration {
+                    self.elapsed = self.duration;
+                    self.times_finished = 1;
+                }
+            }
+            TimerMode::Repeating => {
+                let duration = self.duration.as_nanos();
+                self.times_finished = (self.elapsed.as_nanos() / duration) as u32;
+                self.elapsed = Duration::from_nanos((self.elapsed.as_nanos() % duration) as u64);
+            }
+        }
+
+        self.times_finished
+    }
+
+    /// Finished during the last tick
+    pub fn just_finished(&self) -> bool {
+        self.times_finished > 0
+    }
+
+    /// Only a [`TimerMode::Once`] timer stays finished
+    pub fn is_finished(&self) -> bool {
+        self.mode == TimerMode::Once && self.elapsed >= self.duration
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    /// Elapsed part of the duration, in `[0, 1]`
+    pub fn fraction(&self) -> f32 {
+        (self.elapsed.as_secs_f64() / self.duration.as_secs_f64()) as f32
+    }
+
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.times_finished = 0;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerFinished {
+    pub entity: Entity,
+    /// More than one when a repeating timer finished several times in a frame
+    pub times: u32,
+}
+
+/// Ticks the [`Timer`] components, added to the first stage of the frame
+/// schedule by the [`crate::app_builder::AppBuilder`]
+pub struct TimerSystem;
+
+impl TimerSystem {
+    pub const DEFAULT_NAME: &'static str = "timer_system";
+}
+
+impl<'a> System<'a> for TimerSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Time>,
+        WriteStorage<'a, Timer>,
+        Write<'a, EventChannel<TimerFinished>>,
+    );
+
+    fn run(&mut self, (entities, time, mut timers, mut finished): Self::SystemData) {
+        for (entity, timer) in (&entities, &mut timers).join() {
+            let delta = if timer.unscaled {
+                time.real_delta()
+            } else {
+                time.delta()
+            };
+
+            let times = timer.tick(delta);
+            if times > 0 {
+                finished.single_write(TimerFinished { entity, times });
+            }
+        }
+    }
+}
+
+/// Adds `pause`, `resume`, `step` and `time_scale` to the console
+pub(crate) fn register_time_commands(console: &mut Console) {
+    console.register("pause", "", "Pauses the game time", |world, _| {
+        world.write_resource::<Time>().pause();
+        Ok(String::new())
+    });
+
+    console.register("resume", "", "Resumes the game time", |world, _| {
+        world.write_resource::<Time>().resume();
+        Ok(String::new())
+    });
+
+    console.register(
+        "step",
+        "[frames]",
+        "Advances the paused game time by some frames, 1 by default",
+        |world, args| {
+            let frames = if args.is_empty() {
+                1
+            } else {
+                args.parse::<u32>(0)?
+            };
+
+            let mut time = world.write_resource::<Time>();
+            if !time.is_paused() {
+                bail!("the game time is not paused");
+            }
+            time.step(frames);
+            Ok(String::new())
+        },
+    );
+
+    console.register(
+        "time_scale",
+        "[scale]",
+        "Prints or sets the game time scale",
+        |world, args| {
+            let mut time = world.write_resource::<Time>();
+            if args.is_empty() {
+                return Ok(format!("time_scale = {}", time.scale()));
+            }
+
+            let scale = args.parse::<f64>(0)?;
+            if !scale.is_finite() || scale < 0.0 {
+                bail!("expected a non-negative scale");
+            }
+            time.set_scale(scale);
+            Ok(String::new())
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::Builder;
+
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn scales_pauses_and_steps() {
+        let mut time = Time::default();
+
+        time.set_scale(0.5);
+        time.advance(FRAME);
+        assert_eq!(time.delta(), Duration::from_millis(5));
+
+        time.pause();
+        time.advance(FRAME);
+        assert_eq!(time.delta(), Duration::ZERO);
+
+        time.step(1);
+        time.advance(FRAME);
+        time.advance(FRAME);
+        assert_eq!(time.elapsed(), Duration::from_millis(10));
+        assert_eq!(time.real_elapsed(), Duration::from_millis(40));
+        assert_eq!(time.frame(), 4);
+
+        time.step(u32::MAX);
+        time.step(1);
+        time.advance(FRAME);
+        assert_eq!(time.delta(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn timers_finish_once_or_repeat() {
+        let mut once = Timer::once(Duration::from_millis(25));
+        let mut repeating = Timer::repeating(Duration::from_millis(10));
+
+        assert_eq!(once.tick(Duration::from_millis(20)), 0);
+        assert_eq!(once.tick(Duration::from_millis(20)), 1);
+        assert_eq!(once.tick(Duration::from_millis(20)), 0);
+        assert!(once.is_finished() && !once.just_finished());
+
+        assert_eq!(repeating.tick(Duration::from_millis(25)), 2);
+        assert_eq!(repeating.elapsed(), Duration::from_millis(5));
+        assert!(!repeating.is_finished());
+    }
+
+    #[test]
+    fn timer_system_follows_the_game_clock() {
+        let mut app = crate::App::builder()
+            .name("Headless test")
+            .isolated()
+            .with_frame_pacing(crate::frame_pacing::FramePacing::Uncapped)
+            .build()
+            .unwrap();
+        app.init();
+
+        let world = app.world_mut();
+        let mut reader = world
+            .write_resource::<EventChannel<TimerFinished>>()
+            .register_reader();
+        let scaled = world
+            .create_entity()
+            .with(Timer::once(Duration::from_millis(15)))
+            .build();
+        world
+            .create_entity()
+            .with(Timer::once(Duration::from_millis(15)).with_unscaled(true))
+            .build();
+        world.write_resource::<Time>().pause();
+
+        app.step(FRAME);
+        app.step(FRAME);
+
+        let world = app.world();
+        let finished: Vec<_> = world
+            .read_resource::<EventChannel<TimerFinished>>()
+            .read(&mut reader)
+            .map(|event| event.entity)
+            .collect();
+        assert_eq!(finished.len(), 1);
+        assert_ne!(finished[0], scaled);
+        assert_eq!(
+            world.read_storage::<Timer>().get(scaled).unwrap().elapsed(),
+            Duration::ZERO
+        );
+    }
+}
@@ -1,3 +1,4 @@
+pub use bizarre_common as common;
 pub use bizarre_core as core;
 pub use bizarre_layers as layers;
 pub use bizarre_logger as log;
@@ -1,22 +1,76 @@
+use std::time::Duration;
+
 use bizarre_common::resources::DeltaTime;
 use bizarre_core::{
     core_events::WindowResized,
-    input::{InputHandler, KeyboardKey, KeyboardModifiers, MouseButton},
-    layer::Layer,
-    schedule::{ScheduleBuilder, ScheduleType},
+    input::{
+        ActionBinding, ActionMap, ActionState, AxisBinding, CursorMode, KeyboardKey, MouseAxis,
+        MouseButton,
+    },
+    layer::{Layer, LayerDependency},
+    schedule::{ScheduleBuilder, ScheduleType, Stage, SystemConfig},
 };
 
-use bizarre_logger::{core_debug, core_info, core_warn};
+use bizarre_logger::{core_debug, core_info, core_warn_every, core_warn_once};
 use bizarre_render::{
     render_components::{free_camera::FreeCameraComponent, ActiveCamera, Camera, CameraProjection},
     render_submitter::RenderSubmitter,
 };
-use nalgebra_glm::{vec3, Vec2};
+use nalgebra_glm::{vec3, Vec2, Vec3};
 use specs::{
     shrev::EventChannel, Builder, Join, Read, ReadStorage, ReaderId, RunNow, System, WorldExt,
     Write, WriteStorage,
 };
 
+use crate::{
+    input_layer::{ActionMapSystem, InputLayer},
+    visual_layer::VisualLayer,
+};
+
+/// Actions and axes read by the camera, bound by default unless the loaded
+/// action map has them
+pub mod camera_actions {
+    pub const MOVE_FORWARD: &str = "camera_move_forward";
+    pub const MOVE_RIGHT: &str = "camera_move_right";
+    pub const MOVE_UP: &str = "camera_move_up";
+    pub const LOOK: &str = "camera_look";
+    pub const LOOK_X: &str = "camera_look_x";
+    pub const LOOK_Y: &str = "camera_look_y";
+    pub const VIEW_FRONT: &str = "camera_view_front";
+    pub const VIEW_SIDE: &str = "camera_view_side";
+    pub const VIEW_TOP: &str = "camera_view_top";
+}
+
+fn bind_default_actions(map: &mut ActionMap) {
+    use camera_actions::*;
+
+    let keys = |negative, positive| [AxisBinding::Keys { negative, positive }];
+
+    map.bind_axis_default(MOVE_FORWARD, &keys(KeyboardKey::S, KeyboardKey::W));
+    map.bind_axis_default(MOVE_RIGHT, &keys(KeyboardKey::A, KeyboardKey::D));
+    map.bind_axis_default(MOVE_UP, &keys(KeyboardKey::Q, KeyboardKey::E));
+
+    map.bind_default(LOOK, &[ActionBinding::mouse(MouseButton::Right)]);
+    map.bind_axis_default(
+        LOOK_X,
+        &[AxisBinding::RawMouseMotion {
+            axis: MouseAxis::X,
+            scale: 0.1,
+        }],
+    );
+    map.bind_axis_default(
+        LOOK_Y,
+        &[AxisBinding::RawMouseMotion {
+            axis: MouseAxis::Y,
+            scale: -0.1,
+        }],
+    );
+
+    map.bind_default(VIEW_FRONT, &[ActionBinding::key(KeyboardKey::Z)]);
+    map.bind_default(VIEW_SIDE, &[ActionBinding::key(KeyboardKey::X)]);
+    map.bind_default(VIEW_TOP, &[ActionBinding::key(KeyboardKey::Y)]);
+}
+
 #[derive(Default)]
 struct CameraSystem {
     reader_id: Option<ReaderId<WindowResized>>,
@@ -25,16 +79,24 @@ struct CameraSystem {
 impl<'a> System<'a> for CameraSystem {
     type SystemData = (
         Write<'a, RenderSubmitter>,
-        Read<'a, InputHandler>,
+        Read<'a, ActionState>,
         Read<'a, DeltaTime>,
         Read<'a, EventChannel<WindowResized>>,
+        Write<'a, CursorMode>,
         WriteStorage<'a, FreeCameraComponent>,
         ReadStorage<'a, ActiveCamera>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut submitter, input, delta_time, window_resize_channel, mut cameras, active_camera) =
-            data;
+        let (
+            mut submitter,
+            actions,
+            delta_time,
+            window_resize_channel,
+            mut cursor_mode,
+            mut cameras,
+            active_camera,
+        ) = data;
 
         let delta_time = delta_time.0.as_secs_f32();
         const BASE_CAMERA_SPEED: f32 = 10.0;
@@ -43,16 +105,23 @@ impl<'a> System<'a> for CameraSystem {
 
         let active_camera = active_cameras.next();
 
-        if active_camera.is_none() {
-            core_warn!("ActiveCameraSystem: no active camera found!");
-        } else if active_cameras.next().is_some() {
-            core_warn!(
+        let (camera, _) = match active_camera {
+            Some(camera) => camera,
+            None => {
+                core_warn_every!(
+                    Duration::from_secs(5),
+                    "ActiveCameraSystem: no active camera found!"
+                );
+                return;
+            }
+        };
+
+        if active_cameras.next().is_some() {
+            core_warn_once!(
                 "ActiveCameraSystem: multiple active cameras found! Going with the first one"
             );
         }
 
-        let (camera, _) = active_camera.unwrap();
-
         match window_resize_channel
             .read(&mut self.reader_id.as_mut().unwrap())
             .last()
@@ -63,64 +132,52 @@ impl<'a> System<'a> for CameraSystem {
             None => {}
         };
 
-        if input.is_key_pressed(&KeyboardKey::W, &KeyboardModifiers::empty()) {
-            let direction = {
-                let mut base = camera.forward();
-                base.y = 0.0;
-                base.normalize()
-            };
-            camera.position += direction * BASE_CAMERA_SPEED * delta_time;
-        }
-        if input.is_key_pressed(&KeyboardKey::S, &KeyboardModifiers::empty()) {
-            let direction = {
-                let mut base = camera.forward();
-                base.y = 0.0;
-                base.normalize()
-            };
-            camera.position -= direction * BASE_CAMERA_SPEED * delta_time;
-        }
-        if input.is_key_pressed(&KeyboardKey::A, &KeyboardModifiers::empty()) {
-            let direction = {
-                let mut base = camera.right();
-                base.y = 0.0;
-                base.normalize()
-            };
-            camera.position -= direction * BASE_CAMERA_SPEED * delta_time;
-        }
-        if input.is_key_pressed(&KeyboardKey::D, &KeyboardModifiers::empty()) {
-            let direction = {
-                let mut base = camera.right();
-                base.y = 0.0;
-                base.normalize()
-            };
-            camera.position += direction * BASE_CAMERA_SPEED * delta_time;
-        }
-        if input.is_key_pressed(&KeyboardKey::Q, &KeyboardModifiers::empty()) {
-            camera.position.y -= BASE_CAMERA_SPEED * delta_time;
+        use camera_actions::*;
+
+        let flat = |mut direction: Vec3| {
+            direction.y = 0.0;
+            direction.normalize()
+        };
+
+        let speed = BASE_CAMERA_SPEED * delta_time;
+
+        // Flattening the direction of a camera looking straight down gives NaN
+        let forward = actions.value(MOVE_FORWARD);
+        if forward != 0.0 {
+            camera.position += flat(camera.forward()) * forward * speed;
         }
-        if input.is_key_pressed(&KeyboardKey::E, &KeyboardModifiers::empty()) {
-            camera.position.y += BASE_CAMERA_SPEED * delta_time;
+        let right = actions.value(MOVE_RIGHT);
+        if right != 0.0 {
+            camera.position += flat(camera.right()) * right * speed;
         }
+        camera.position.y += actions.value(MOVE_UP) * speed;
 
-        if input.is_key_pressed(&KeyboardKey::Z, &KeyboardModifiers::empty()) {
+        if actions.pressed(VIEW_FRONT) {
             camera.yaw = 180.0;
             camera.pitch = 0.0;
         }
-        if input.is_key_pressed(&KeyboardKey::X, &KeyboardModifiers::empty()) {
+        if actions.pressed(VIEW_SIDE) {
             camera.yaw = 90.0;
             camera.pitch = 0.0;
         }
-        if input.is_key_pressed(&KeyboardKey::Y, &KeyboardModifiers::empty()) {
+        if actions.pressed(VIEW_TOP) {
             camera.yaw = 0.0;
             camera.pitch = 90.0;
         }
 
-        if input.is_button_pressed(&MouseButton::Right, &KeyboardModifiers::empty()) {
-            let mouse_delta = input.mouse_delta();
+        // Only on the edges, so the mode set by other systems sticks
+        if actions.just_pressed(LOOK) {
+            *cursor_mode = CursorMode::Locked;
+        } else if actions.just_released(LOOK) {
+            *cursor_mode = CursorMode::Free;
+        }
+
+        if actions.pressed(LOOK) {
+            let look = Vec2::new(actions.value(LOOK_X), actions.value(LOOK_Y));
 
-            if mouse_delta != Vec2::zeros() {
-                camera.yaw += mouse_delta.x * 0.1;
-                camera.pitch += -mouse_delta.y * 0.1;
+            if look != Vec2::zeros() {
+                camera.yaw += look.x;
+                camera.pitch += look.y;
                 camera.pitch = camera.pitch.clamp(-89.0, 89.0);
             }
         }
@@ -147,6 +204,8 @@ impl Layer for CameraLayer {
         &mut self,
         app_builder: &mut bizarre_core::app_builder::AppBuilder,
     ) -> anyhow::Result<()> {
+        bind_default_actions(&mut app_builder.world.write_resource::<ActionMap>());
+
         app_builder.world.register::<FreeCameraComponent>();
         app_builder.world.register::<ActiveCamera>();
 
@@ -168,13 +227,21 @@ impl Layer for CameraLayer {
             .with(ActiveCamera)
             .build();
 
-        app_builder.add_system(
+        app_builder.add_configured_system(
             ScheduleType::Frame,
             CameraSystem::default(),
-            "camera_system",
-            &[],
+            SystemConfig::new("camera_system")
+                .stage(Stage::Update)
+                .after(ActionMapSystem::DEFAULT_NAME),
         );
 
         Ok(())
     }
+
+    fn dependencies(&self) -> Vec<LayerDependency> {
+        vec![
+            LayerDependency::of::<InputLayer>(),
+            LayerDependency::of::<VisualLayer>(),
+        ]
+    }
 }
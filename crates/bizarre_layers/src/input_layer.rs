@@ -1,14 +1,39 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
+use bizarre_common::resources::DeltaTime;
 use bizarre_core::{
     app_builder::AppBuilder,
-    input::{InputHandler, KeyboardEvent, MouseEvent},
+    engine_config::EngineConfig,
+    input::{
+        ActionMap, ActionState, CursorMode, GamepadEvent, InputHandler, InputPlayer, InputRecorder,
+        InputRecording, KeyboardEvent, MouseEvent, TextInputEvent,
+    },
     layer::Layer,
-    schedule::{ScheduleBuilder, ScheduleType},
+    schedule::{ScheduleBuilder, ScheduleType, Stage, SystemConfig},
 };
-use specs::{shrev::EventChannel, ReaderId, System, WorldExt, Write};
+use bizarre_logger::{core_error, core_info};
+use specs::{shrev::EventChannel, Read, ReaderId, System, WorldExt, Write};
 
 use crate::visual_layer::WinitEventSystem;
 
+/// Overrides the action map path looked up by the [`InputLayer`] and the
+/// `input.action_map` key of the engine config
+pub const ACTION_MAP_ENV_VAR: &str = "BIZARRE_ACTION_MAP";
+
+/// Looked up in the working directory when the env var isn't set
+const DEFAULT_ACTION_MAP_PATHS: [&str; 2] = ["input.toml", "input.ron"];
+
+/// The input of every frame is recorded to this path when set, and saved
+/// when the layer is detached
+pub const INPUT_RECORD_ENV_VAR: &str = "BIZARRE_INPUT_RECORD";
+
+/// The recording at this path is played back instead of the device input
+pub const INPUT_PLAYBACK_ENV_VAR: &str = "BIZARRE_INPUT_PLAYBACK";
+
+#[derive(Default)]
+struct InputRecordPath(Option<PathBuf>);
+
 #[derive(Default)]
 pub struct InputLayer;
 
@@ -24,25 +49,194 @@ impl<'a> System<'a> for InputHandlerUpdate {
         Write<'a, InputHandler>,
         Write<'a, EventChannel<MouseEvent>>,
         Write<'a, EventChannel<KeyboardEvent>>,
+        Write<'a, EventChannel<GamepadEvent>>,
+        Write<'a, EventChannel<TextInputEvent>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut handler, mut mouse, mut keyboard) = data;
-        handler.update(&mut (&mut mouse, &mut keyboard));
+        let (mut handler, mut mouse, mut keyboard, mut gamepad, mut text) = data;
+        handler.update(&mut (&mut mouse, &mut keyboard, &mut gamepad, &mut text));
+    }
+}
+
+/// Evaluates the [`ActionMap`] into the [`ActionState`]. Runs at the start of
+/// [`Stage::Update`], after the window events came in.
+#[derive(Default)]
+pub struct ActionMapSystem;
+
+impl ActionMapSystem {
+    pub const DEFAULT_NAME: &'static str = "action_map_system";
+}
+
+impl<'a> System<'a> for ActionMapSystem {
+    type SystemData = (
+        Read<'a, ActionMap>,
+        Read<'a, InputHandler>,
+        Write<'a, ActionState>,
+    );
+
+    fn run(&mut self, (map, input, mut state): Self::SystemData) {
+        state.update(&map, &input);
+    }
+}
+
+/// Stores the raw input of the frame in the [`InputRecorder`]. Runs in
+/// [`Stage::Update`], after the window events came in.
+#[derive(Default)]
+pub struct InputRecordSystem;
+
+impl InputRecordSystem {
+    pub const DEFAULT_NAME: &'static str = "input_record_system";
+}
+
+impl<'a> System<'a> for InputRecordSystem {
+    type SystemData = (
+        Read<'a, InputHandler>,
+        Read<'a, DeltaTime>,
+        Write<'a, InputRecorder>,
+    );
+
+    fn run(&mut self, (input, delta_time, mut recorder): Self::SystemData) {
+        recorder.record_frame(&input, delta_time.0);
+    }
+}
+
+/// Feeds the next frame of the [`InputPlayer`] to the [`InputHandler`]. The
+/// app already advanced the time by the recorded delta of the frame.
+#[derive(Default)]
+pub struct InputPlaybackSystem;
+
+impl InputPlaybackSystem {
+    pub const DEFAULT_NAME: &'static str = "input_playback_system";
+}
+
+impl<'a> System<'a> for InputPlaybackSystem {
+    type SystemData = (Write<'a, InputPlayer>, Write<'a, InputHandler>);
+
+    fn run(&mut self, (mut player, mut input): Self::SystemData) {
+        if player.is_finished() {
+            return;
+        }
+
+        player.play_frame(&mut input);
+
+        if player.is_finished() {
+            core_info!("Input playback finished");
+        }
+    }
+}
+
+fn load_input_player() -> InputPlayer {
+    let path = match std::env::var_os(INPUT_PLAYBACK_ENV_VAR) {
+        Some(path) => PathBuf::from(path),
+        None => return InputPlayer::default(),
+    };
+
+    match InputRecording::load(&path) {
+        Ok(recording) => {
+            core_info!(
+                "Playing back {} frames of input from {:?}",
+                recording.frames.len(),
+                path
+            );
+            InputPlayer::new(recording)
+        }
+        Err(err) => {
+            core_error!("{}", err);
+            InputPlayer::default()
+        }
+    }
+}
+
+fn load_action_map(config: &EngineConfig) -> ActionMap {
+    let path = std::env::var_os(ACTION_MAP_ENV_VAR)
+        .map(PathBuf::from)
+        .or_else(|| config.get_or::<Option<PathBuf>>("input.action_map", None))
+        .or_else(|| {
+            DEFAULT_ACTION_MAP_PATHS
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.is_file())
+        });
+
+    let path = match path {
+        Some(path) => path,
+        None => return ActionMap::default(),
+    };
+
+    match ActionMap::load(&path) {
+        Ok(map) => {
+            core_info!("Loaded the action map from {:?}", path);
+            map
+        }
+        Err(err) => {
+            core_error!("{}", err);
+            ActionMap::default()
+        }
     }
 }
 
 impl Layer for InputLayer {
     fn on_attach(&mut self, app_builder: &mut AppBuilder) -> Result<()> {
         app_builder.world.insert(InputHandler::default());
+        let action_map = load_action_map(&app_builder.config);
+        app_builder.world.insert(action_map);
+        app_builder.world.insert(ActionState::default());
+        app_builder.world.insert(CursorMode::default());
+        app_builder.world.insert(load_input_player());
 
-        app_builder.add_system(
+        let record_path = std::env::var_os(INPUT_RECORD_ENV_VAR).map(PathBuf::from);
+        let mut recorder = InputRecorder::default();
+        if let Some(path) = &record_path {
+            core_info!("Recording the input to {:?}", path);
+            recorder.start();
+        }
+        app_builder.world.insert(recorder);
+        app_builder.world.insert(InputRecordPath(record_path));
+
+        // Rolls the input state over before the new window events come in
+        app_builder.add_configured_system(
             ScheduleType::Frame,
             InputHandlerUpdate,
-            InputHandlerUpdate::DEFAULT_NAME,
-            &[],
+            SystemConfig::new(InputHandlerUpdate::DEFAULT_NAME).stage(Stage::First),
+        );
+
+        app_builder.add_configured_system(
+            ScheduleType::Frame,
+            InputPlaybackSystem,
+            SystemConfig::new(InputPlaybackSystem::DEFAULT_NAME)
+                .stage(Stage::First)
+                .after(InputHandlerUpdate::DEFAULT_NAME),
         );
 
+        app_builder.add_configured_system(
+            ScheduleType::Frame,
+            InputRecordSystem,
+            SystemConfig::new(InputRecordSystem::DEFAULT_NAME).stage(Stage::Update),
+        );
+
+        app_builder.add_configured_system(
+            ScheduleType::Frame,
+            ActionMapSystem,
+            SystemConfig::new(ActionMapSystem::DEFAULT_NAME).stage(Stage::Update),
+        );
+
+        Ok(())
+    }
+
+    fn on_detach(&mut self, world: &mut specs::World) -> Result<()> {
+        let path = world.write_resource::<InputRecordPath>().0.take();
+
+        if let Some(path) = path {
+            let recording = world.write_resource::<InputRecorder>().stop();
+            recording.save(&path)?;
+            core_info!(
+                "Saved {} frames of input to {:?}",
+                recording.frames.len(),
+                path
+            );
+        }
+
         Ok(())
     }
 }
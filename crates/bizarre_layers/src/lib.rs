@@ -1,3 +1,5 @@
 pub mod input_layer;
 pub mod camera_layer;
 pub mod visual_layer;
+
+mod winit_keys;
@@ -7,12 +7,12 @@ use anyhow::Result;
 use bizarre_core::{
     app_builder::AppBuilder,
     app_events::AppCloseRequestedEvent,
-    core_events::WindowResized,
-    input::{input_handler, InputHandler, MouseButton},
-    layer::Layer,
-    schedule::ScheduleType,
+    core_events::{WindowFocusChanged, WindowResized},
+    input::{input_handler, CursorMode, InputHandler, InputPlayer, MouseButton},
+    layer::{Layer, LayerDependency},
+    schedule::{ScheduleType, Stage, SystemConfig},
 };
-use bizarre_logger::core_debug;
+use bizarre_logger::{core_debug, core_warn};
 use bizarre_render::{
     material_loader::MaterialLoader,
     render_components::{MeshComponent, WindowComponent},
@@ -22,7 +22,8 @@ use bizarre_render::{
         MeshDrawRequestSystem, MeshManagementSystem, RendererResource, RendererUpdateSystem,
     },
     scene::RenderScene,
-    Renderer,
+    vulkan_utils::shader::{set_shader_cache_dir, DEFAULT_SHADER_CACHE_DIR},
+    Renderer, RendererSettings,
 };
 use specs::{
     shrev::EventChannel, Builder, Join, Read, ReadStorage, ReaderId, System, SystemData, WorldExt,
@@ -30,23 +31,46 @@ use specs::{
 };
 use winit::{
     dpi::LogicalSize,
-    platform::{pump_events::EventLoopExtPumpEvents, scancode::PhysicalKeyExtScancode},
+    platform::{
+        modifier_supplement::KeyEventExtModifierSupplement, pump_events::EventLoopExtPumpEvents,
+    },
 };
 
+use crate::{input_layer::InputLayer, winit_keys};
+
+/// Reads `window.width`, `window.height`, `window.title`,
+/// `render.msaa_samples` and `render.shader_cache_dir` from the engine config
 #[derive(Default)]
 pub struct VisualLayer;
 
 impl Layer for VisualLayer {
     fn on_attach(&mut self, app_builder: &mut AppBuilder) -> Result<()> {
+        let config = &app_builder.config;
+
         let event_loop = winit::event_loop::EventLoop::new()?;
 
         let window = winit::window::WindowBuilder::new()
             .with_active(true)
-            .with_title("Bizarre Engine")
-            .with_inner_size(LogicalSize::new(800, 600))
+            .with_title(config.get_or("window.title", String::from("Bizarre Engine")))
+            .with_inner_size(LogicalSize::new(
+                config.get_or("window.width", 800u32),
+                config.get_or("window.height", 600u32),
+            ))
             .build(&event_loop)?;
 
-        let renderer = Renderer::new(&window)?;
+        set_shader_cache_dir(config.get_or(
+            "render.shader_cache_dir",
+            String::from(DEFAULT_SHADER_CACHE_DIR),
+        ));
+
+        let settings = RendererSettings {
+            msaa_samples: config.get_or(
+                "render.msaa_samples",
+                RendererSettings::default().msaa_samples,
+            ),
+        };
+
+        let renderer = Renderer::new(&window, &settings)?;
         let render_scene = RenderScene::new(renderer.max_frames_in_flight, &renderer.device)?;
 
         let event_loop = WinitEventLoopResource(Arc::new(Mutex::new(event_loop)));
@@ -67,11 +91,10 @@ impl Layer for VisualLayer {
             .with(WindowComponent { handle: window })
             .build();
 
-        app_builder.add_system(
+        app_builder.add_configured_system(
             ScheduleType::Frame,
-            WinitEventSystem,
-            WinitEventSystem::DEFAULT_NAME,
-            &[],
+            WinitEventSystem::default(),
+            SystemConfig::new(WinitEventSystem::DEFAULT_NAME).stage(Stage::PreUpdate),
         );
 
         app_builder.world.register::<MeshComponent>();
@@ -84,45 +107,66 @@ impl Layer for VisualLayer {
             let mesh_management_system = MeshManagementSystem {
                 reader_id: mesh_reader,
             };
-            app_builder.add_system(
+            app_builder.add_configured_system(
                 ScheduleType::Frame,
                 mesh_management_system,
-                MeshManagementSystem::DEFAULT_NAME,
-                &[],
+                SystemConfig::new(MeshManagementSystem::DEFAULT_NAME).stage(Stage::PostUpdate),
             );
         }
 
-        app_builder.add_system(
+        app_builder.add_configured_system(
             ScheduleType::Frame,
             MeshDrawRequestSystem::default(),
-            MeshDrawRequestSystem::DEFAULT_NAME,
-            &[MeshManagementSystem::DEFAULT_NAME],
+            SystemConfig::new(MeshDrawRequestSystem::DEFAULT_NAME)
+                .stage(Stage::PostUpdate)
+                .after(MeshManagementSystem::DEFAULT_NAME),
         );
-        app_builder.add_system(
+        app_builder.add_configured_system(
             ScheduleType::Frame,
             LightSystem,
-            LightSystem::DEFAULT_NAME,
-            &[],
+            SystemConfig::new(LightSystem::DEFAULT_NAME).stage(Stage::PostUpdate),
         );
-        app_builder.add_system(
+        app_builder.add_configured_system(
             ScheduleType::Frame,
             RendererResizeSystem::default(),
-            "renderer_resize",
-            &[],
+            SystemConfig::new("renderer_resize")
+                .stage(Stage::PreUpdate)
+                .after(WinitEventSystem::DEFAULT_NAME),
         );
-        app_builder.add_barrier(ScheduleType::Frame);
-        app_builder.add_system(
+        app_builder.add_try_system(
             ScheduleType::Frame,
             RendererUpdateSystem,
-            RendererUpdateSystem::DEFAULT_NAME,
-            &[
-                MeshManagementSystem::DEFAULT_NAME,
-                LightSystem::DEFAULT_NAME,
-            ],
+            SystemConfig::new(RendererUpdateSystem::DEFAULT_NAME).stage(Stage::Render),
         );
 
         Ok(())
     }
+
+    fn on_detach(&mut self, world: &mut specs::World) -> Result<()> {
+        let renderer = match world.remove::<RendererResource>() {
+            Some(renderer) => renderer,
+            None => return Ok(()),
+        };
+        let mut renderer = renderer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("The renderer mutex is poisoned"))?;
+
+        unsafe { renderer.device.device_wait_idle()? };
+
+        if let Some(mut scene) = world.remove::<RenderScene>() {
+            scene.destroy(&renderer.device);
+        }
+        if let Some(mut material_loader) = world.remove::<MaterialLoader>() {
+            material_loader.destroy(&renderer.device);
+        }
+
+        renderer.destroy();
+        Ok(())
+    }
+
+    fn dependencies(&self) -> Vec<LayerDependency> {
+        vec![LayerDependency::of::<InputLayer>()]
+    }
 }
 
 #[derive(Default)]
@@ -194,24 +238,65 @@ impl<'a> System<'a> for LightSystem {
 }
 
 #[derive(Default)]
-pub struct WinitEventSystem;
+pub struct WinitEventSystem {
+    ime_allowed: bool,
+    /// `None` until applied, and again after the window lost focus since
+    /// the OS may release the grab
+    cursor_mode: Option<CursorMode>,
+}
 
 impl WinitEventSystem {
     pub const DEFAULT_NAME: &'static str = "winit_event_system";
 }
 
 impl WinitEventSystem {
+    fn apply_cursor_mode(window: &winit::window::Window, mode: CursorMode) {
+        use winit::window::CursorGrabMode;
+
+        // X11 and Windows can't lock the cursor, macOS can't confine it
+        let (grab, fallback) = match mode {
+            CursorMode::Free => (CursorGrabMode::None, CursorGrabMode::None),
+            CursorMode::Confined => (CursorGrabMode::Confined, CursorGrabMode::Locked),
+            CursorMode::Locked => (CursorGrabMode::Locked, CursorGrabMode::Confined),
+        };
+
+        let result = window
+            .set_cursor_grab(grab)
+            .or_else(|_| window.set_cursor_grab(fallback));
+
+        if let Err(err) = result {
+            core_warn!("Could not apply the cursor mode {:?}: {}", mode, err);
+        }
+
+        window.set_cursor_visible(mode != CursorMode::Locked);
+    }
+
+    /// Device input is dropped while `device_input` is false, e.g. during
+    /// input playback
     fn handle_event<E: 'static>(
         event: winit::event::Event<E>,
+        device_input: bool,
         data: &mut (
             &mut InputHandler,
             &mut EventChannel<AppCloseRequestedEvent>,
             &mut EventChannel<WindowResized>,
+            &mut EventChannel<WindowFocusChanged>,
         ),
     ) {
         use winit::event as w_event;
 
-        let (input_handler, app_close_channel, window_resize_channel) = data;
+        let (input_handler, app_close_channel, window_resize_channel, window_focus_channel) = data;
+
+        if let w_event::Event::DeviceEvent {
+            event: w_event::DeviceEvent::MouseMotion { delta },
+            ..
+        } = &event
+        {
+            if device_input {
+                input_handler.process_raw_mouse_motion([delta.0 as f32, delta.1 as f32]);
+            }
+            return;
+        }
 
         if let w_event::Event::WindowEvent { event, .. } = event {
             match event {
@@ -225,22 +310,39 @@ impl WinitEventSystem {
                         height: size[1] as f32,
                     });
                 }
+                w_event::WindowEvent::Focused(focused) => {
+                    window_focus_channel.single_write(WindowFocusChanged { focused });
+                }
+                w_event::WindowEvent::KeyboardInput { .. }
+                | w_event::WindowEvent::Ime(..)
+                | w_event::WindowEvent::CursorMoved { .. }
+                | w_event::WindowEvent::MouseInput { .. }
+                | w_event::WindowEvent::MouseWheel { .. }
+                    if !device_input => {}
                 w_event::WindowEvent::KeyboardInput { event: input, .. } => {
-                    let keycode = match input.physical_key {
-                        winit::keyboard::PhysicalKey::Code(code) => {
-                            code.to_scancode().unwrap() as u16
-                        }
-                        winit::keyboard::PhysicalKey::Unidentified(code) => match code {
-                            winit::keyboard::NativeKeyCode::Xkb(code) => code as u16,
-                            _ => u16::MAX,
-                        },
-                    };
+                    let key = winit_keys::keyboard_key(input.physical_key);
                     let pressed = match input.state {
                         w_event::ElementState::Pressed => true,
                         w_event::ElementState::Released => false,
                     };
-                    input_handler.process_keyboard(keycode, pressed);
+                    if input.repeat {
+                        input_handler.process_key_repeat(key);
+                    } else {
+                        let logical = winit_keys::logical_key(&input.key_without_modifiers());
+                        input_handler.process_keyboard_with_logical(key, logical, pressed);
+                    }
+                    if let Some(text) = input.text.as_ref().filter(|_| pressed) {
+                        input_handler.process_text(text);
+                    }
                 }
+                w_event::WindowEvent::Ime(ime) => match ime {
+                    w_event::Ime::Commit(text) => input_handler.process_text(&text),
+                    w_event::Ime::Preedit(text, cursor) => {
+                        input_handler.process_preedit(&text, cursor)
+                    }
+                    w_event::Ime::Disabled => input_handler.process_preedit("", None),
+                    w_event::Ime::Enabled => (),
+                },
                 w_event::WindowEvent::CursorMoved { position, .. } => {
                     input_handler.process_mouse_move([position.x as f32, position.y as f32].into());
                 }
@@ -282,19 +384,69 @@ impl<'a> specs::System<'a> for WinitEventSystem {
         Write<'a, InputHandler>,
         Write<'a, EventChannel<AppCloseRequestedEvent>>,
         Write<'a, EventChannel<WindowResized>>,
+        Write<'a, EventChannel<WindowFocusChanged>>,
+        Read<'a, InputPlayer>,
+        Read<'a, CursorMode>,
+        ReadStorage<'a, WindowComponent>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut event_loop, mut input_handler, mut app_close_eq, mut window_resize_eq) = data;
+        let (
+            mut event_loop,
+            mut input_handler,
+            mut app_close_eq,
+            mut window_resize_eq,
+            mut window_focus_eq,
+            input_player,
+            cursor_mode,
+            windows,
+        ) = data;
+
+        let device_input = input_player.is_finished();
 
         let mut event_loop = event_loop.0.lock().unwrap();
 
+        let mut focus_gained = false;
+
         event_loop.pump_events(Some(Duration::ZERO), |event, ewlt| {
+            if let winit::event::Event::WindowEvent {
+                event: winit::event::WindowEvent::Focused(true),
+                ..
+            } = &event
+            {
+                focus_gained = true;
+            }
+
             Self::handle_event(
                 event,
-                &mut (&mut input_handler, &mut app_close_eq, &mut window_resize_eq),
+                device_input,
+                &mut (
+                    &mut input_handler,
+                    &mut app_close_eq,
+                    &mut window_resize_eq,
+                    &mut window_focus_eq,
+                ),
             );
         });
+
+        // Follows the text input mode toggled by the systems last frame
+        let text_input = input_handler.is_text_input_enabled();
+        if text_input != self.ime_allowed {
+            for window in windows.join() {
+                window.handle.set_ime_allowed(text_input);
+            }
+            self.ime_allowed = text_input;
+        }
+
+        if focus_gained {
+            self.cursor_mode = None;
+        }
+        if self.cursor_mode != Some(*cursor_mode) {
+            for window in windows.join() {
+                Self::apply_cursor_mode(&window.handle, *cursor_mode);
+            }
+            self.cursor_mode = Some(*cursor_mode);
+        }
     }
 }
 
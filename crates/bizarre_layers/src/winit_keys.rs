@@ -0,0 +1,151 @@
+use bizarre_core::input::{KeyboardKey, LogicalKey};
+use winit::{
+    keyboard::{Key, KeyCode, NativeKeyCode, PhysicalKey},
+    platform::scancode::PhysicalKeyExtScancode,
+};
+
+/// Codes winit doesn't name end up as [`KeyboardKey::Unidentified`] with the
+/// native scancode, or `u32::MAX` when there is none
+pub(crate) fn keyboard_key(key: PhysicalKey) -> KeyboardKey {
+    let code = match key {
+        PhysicalKey::Code(code) => code,
+        PhysicalKey::Unidentified(native) => {
+            let code = match native {
+                NativeKeyCode::Xkb(code) | NativeKeyCode::Android(code) => code,
+                NativeKeyCode::Windows(code) | NativeKeyCode::MacOS(code) => code as u32,
+                NativeKeyCode::Unidentified => u32::MAX,
+            };
+            return KeyboardKey::Unidentified(code);
+        }
+    };
+
+    match code {
+        KeyCode::KeyQ => KeyboardKey::Q,
+        KeyCode::KeyW => KeyboardKey::W,
+        KeyCode::KeyE => KeyboardKey::E,
+        KeyCode::KeyR => KeyboardKey::R,
+        KeyCode::KeyT => KeyboardKey::T,
+        KeyCode::KeyY => KeyboardKey::Y,
+        KeyCode::KeyU => KeyboardKey::U,
+        KeyCode::KeyI => KeyboardKey::I,
+        KeyCode::KeyO => KeyboardKey::O,
+        KeyCode::KeyP => KeyboardKey::P,
+        KeyCode::KeyA => KeyboardKey::A,
+        KeyCode::KeyS => KeyboardKey::S,
+        KeyCode::KeyD => KeyboardKey::D,
+        KeyCode::KeyF => KeyboardKey::F,
+        KeyCode::KeyG => KeyboardKey::G,
+        KeyCode::KeyH => KeyboardKey::H,
+        KeyCode::KeyJ => KeyboardKey::J,
+        KeyCode::KeyK => KeyboardKey::K,
+        KeyCode::KeyL => KeyboardKey::L,
+        KeyCode::KeyZ => KeyboardKey::Z,
+        KeyCode::KeyX => KeyboardKey::X,
+        KeyCode::KeyC => KeyboardKey::C,
+        KeyCode::KeyV => KeyboardKey::V,
+        KeyCode::KeyB => KeyboardKey::B,
+        KeyCode::KeyN => KeyboardKey::N,
+        KeyCode::KeyM => KeyboardKey::M,
+        KeyCode::Digit1 => KeyboardKey::Digit1,
+        KeyCode::Digit2 => KeyboardKey::Digit2,
+        KeyCode::Digit3 => KeyboardKey::Digit3,
+        KeyCode::Digit4 => KeyboardKey::Digit4,
+        KeyCode::Digit5 => KeyboardKey::Digit5,
+        KeyCode::Digit6 => KeyboardKey::Digit6,
+        KeyCode::Digit7 => KeyboardKey::Digit7,
+        KeyCode::Digit8 => KeyboardKey::Digit8,
+        KeyCode::Digit9 => KeyboardKey::Digit9,
+        KeyCode::Digit0 => KeyboardKey::Digit0,
+        KeyCode::Escape => KeyboardKey::Escape,
+        KeyCode::Backspace => KeyboardKey::Backspace,
+        KeyCode::Enter => KeyboardKey::Enter,
+        KeyCode::Space => KeyboardKey::Space,
+        KeyCode::Tab => KeyboardKey::Tab,
+        KeyCode::CapsLock => KeyboardKey::CapsLock,
+        KeyCode::ShiftRight => KeyboardKey::RShift,
+        KeyCode::ShiftLeft => KeyboardKey::LShift,
+        KeyCode::ControlRight => KeyboardKey::RCtrl,
+        KeyCode::ControlLeft => KeyboardKey::LCtrl,
+        KeyCode::AltRight => KeyboardKey::RAlt,
+        KeyCode::AltLeft => KeyboardKey::LAlt,
+        KeyCode::SuperLeft => KeyboardKey::LSuper,
+        KeyCode::SuperRight => KeyboardKey::RSuper,
+        KeyCode::ContextMenu => KeyboardKey::ContextMenu,
+        KeyCode::Insert => KeyboardKey::Insert,
+        KeyCode::Delete => KeyboardKey::Delete,
+        KeyCode::Home => KeyboardKey::Home,
+        KeyCode::End => KeyboardKey::End,
+        KeyCode::PageUp => KeyboardKey::PageUp,
+        KeyCode::PageDown => KeyboardKey::PageDown,
+        KeyCode::ArrowUp => KeyboardKey::ArrowUp,
+        KeyCode::ArrowDown => KeyboardKey::ArrowDown,
+        KeyCode::ArrowLeft => KeyboardKey::ArrowLeft,
+        KeyCode::ArrowRight => KeyboardKey::ArrowRight,
+        KeyCode::BracketLeft => KeyboardKey::LeftBracket,
+        KeyCode::BracketRight => KeyboardKey::RightBracket,
+        KeyCode::Semicolon => KeyboardKey::SemiColon,
+        KeyCode::Quote => KeyboardKey::Apostrophe,
+        KeyCode::Comma => KeyboardKey::Comma,
+        KeyCode::Period => KeyboardKey::Period,
+        KeyCode::Slash => KeyboardKey::Slash,
+        KeyCode::Backslash => KeyboardKey::BackSlash,
+        KeyCode::Minus => KeyboardKey::Minus,
+        KeyCode::Equal => KeyboardKey::Equal,
+        KeyCode::IntlBackslash => KeyboardKey::IntlBackslash,
+        KeyCode::Backquote => KeyboardKey::GraveAccent,
+        KeyCode::F1 => KeyboardKey::F1,
+        KeyCode::F2 => KeyboardKey::F2,
+        KeyCode::F3 => KeyboardKey::F3,
+        KeyCode::F4 => KeyboardKey::F4,
+        KeyCode::F5 => KeyboardKey::F5,
+        KeyCode::F6 => KeyboardKey::F6,
+        KeyCode::F7 => KeyboardKey::F7,
+        KeyCode::F8 => KeyboardKey::F8,
+        KeyCode::F9 => KeyboardKey::F9,
+        KeyCode::F10 => KeyboardKey::F10,
+        KeyCode::F11 => KeyboardKey::F11,
+        KeyCode::F12 => KeyboardKey::F12,
+        KeyCode::F13 => KeyboardKey::F13,
+        KeyCode::F14 => KeyboardKey::F14,
+        KeyCode::F15 => KeyboardKey::F15,
+        KeyCode::F16 => KeyboardKey::F16,
+        KeyCode::F17 => KeyboardKey::F17,
+        KeyCode::F18 => KeyboardKey::F18,
+        KeyCode::F19 => KeyboardKey::F19,
+        KeyCode::F20 => KeyboardKey::F20,
+        KeyCode::F21 => KeyboardKey::F21,
+        KeyCode::F22 => KeyboardKey::F22,
+        KeyCode::F23 => KeyboardKey::F23,
+        KeyCode::F24 => KeyboardKey::F24,
+        KeyCode::NumLock => KeyboardKey::NumLock,
+        KeyCode::Numpad0 => KeyboardKey::Numpad0,
+        KeyCode::Numpad1 => KeyboardKey::Numpad1,
+        KeyCode::Numpad2 => KeyboardKey::Numpad2,
+        KeyCode::Numpad3 => KeyboardKey::Numpad3,
+        KeyCode::Numpad4 => KeyboardKey::Numpad4,
+        KeyCode::Numpad5 => KeyboardKey::Numpad5,
+        KeyCode::Numpad6 => KeyboardKey::Numpad6,
+        KeyCode::Numpad7 => KeyboardKey::Numpad7,
+        KeyCode::Numpad8 => KeyboardKey::Numpad8,
+        KeyCode::Numpad9 => KeyboardKey::Numpad9,
+        KeyCode::NumpadAdd => KeyboardKey::NumpadAdd,
+        KeyCode::NumpadSubtract => KeyboardKey::NumpadSubtract,
+        KeyCode::NumpadMultiply => KeyboardKey::NumpadMultiply,
+        KeyCode::NumpadDivide => KeyboardKey::NumpadDivide,
+        KeyCode::NumpadDecimal => KeyboardKey::NumpadDecimal,
+        KeyCode::NumpadEnter => KeyboardKey::NumpadEnter,
+        KeyCode::PrintScreen => KeyboardKey::PrintScreen,
+        KeyCode::ScrollLock => KeyboardKey::ScrollLock,
+        KeyCode::Pause => KeyboardKey::Pause,
+        code => KeyboardKey::Unidentified(code.to_scancode().unwrap_or(u32::MAX)),
+    }
+}
+
+/// Expects the key without modifiers, so `S` with Ctrl held reads as `s`
+pub(crate) fn logical_key(key: &Key) -> LogicalKey {
+    match key {
+        Key::Character(text) => LogicalKey::Character(text.to_string()),
+        Key::Named(named) => LogicalKey::Named(format!("{:?}", named)),
+        Key::Unidentified(_) | Key::Dead(_) => LogicalKey::Unidentified,
+    }
+}
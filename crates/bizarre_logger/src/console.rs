@@ -0,0 +1,126 @@
+use std::io::IsTerminal;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{log_level::LogLevel, TerminalEscapeSequence};
+
+/// Controls whether console targets get ANSI escape sequences
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorMode {
+    /// Color only when the stream is a terminal, honoring `NO_COLOR` and
+    /// `CLICOLOR_FORCE`
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleStream {
+    Stdout,
+    Stderr,
+}
+
+impl ConsoleStream {
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            ConsoleStream::Stdout => std::io::stdout().is_terminal(),
+            ConsoleStream::Stderr => std::io::stderr().is_terminal(),
+        }
+    }
+}
+
+impl ColorMode {
+    pub fn colors_enabled(&self, stream: ConsoleStream) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => auto_colors_enabled(
+                std::env::var("NO_COLOR").ok().as_deref(),
+                std::env::var("CLICOLOR_FORCE").ok().as_deref(),
+                stream.is_terminal(),
+            ),
+        }
+    }
+}
+
+/// See <https://no-color.org> and <https://bixense.com/clicolors>
+fn auto_colors_enabled(no_color: Option<&str>, clicolor_force: Option<&str>, tty: bool) -> bool {
+    if no_color.is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+
+    if clicolor_force.is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+
+    tty
+}
+
+/// Per-level escape sequences used for console targets
+#[derive(Debug, Clone)]
+pub struct ColorTheme {
+    pub debug: TerminalEscapeSequence,
+    pub info: TerminalEscapeSequence,
+    pub warn: TerminalEscapeSequence,
+    pub error: TerminalEscapeSequence,
+    pub critical: TerminalEscapeSequence,
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        Self {
+            debug: TerminalEscapeSequence::from(&LogLevel::Debug),
+            info: TerminalEscapeSequence::from(&LogLevel::Info),
+            warn: TerminalEscapeSequence::from(&LogLevel::Warn),
+            error: TerminalEscapeSequence::from(&LogLevel::Error),
+            critical: TerminalEscapeSequence::from(&LogLevel::Critical),
+        }
+    }
+}
+
+impl ColorTheme {
+    pub fn get(&self, level: &LogLevel) -> &TerminalEscapeSequence {
+        match level {
+            LogLevel::Debug => &self.debug,
+            LogLevel::Info => &self.info,
+            LogLevel::Warn => &self.warn,
+            LogLevel::Error => &self.error,
+            LogLevel::Critical => &self.critical,
+        }
+    }
+
+    pub fn with_level(mut self, level: LogLevel, sequence: TerminalEscapeSequence) -> Self {
+        match level {
+            LogLevel::Debug => self.debug = sequence,
+            LogLevel::Info => self.info = sequence,
+            LogLevel::Warn => self.warn = sequence,
+            LogLevel::Error => self.error = sequence,
+            LogLevel::Critical => self.critical = sequence,
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::auto_colors_enabled;
+
+    #[test]
+    fn tty_decides_without_env() {
+        assert!(auto_colors_enabled(None, None, true));
+        assert!(!auto_colors_enabled(None, None, false));
+    }
+
+    #[test]
+    fn no_color_wins() {
+        assert!(!auto_colors_enabled(Some("1"), Some("1"), true));
+        assert!(auto_colors_enabled(Some(""), None, true));
+    }
+
+    #[test]
+    fn clicolor_force_enables_without_tty() {
+        assert!(auto_colors_enabled(None, Some("1"), false));
+        assert!(!auto_colors_enabled(None, Some("0"), false));
+    }
+}
@@ -1,47 +1,76 @@
 use std::{
+    collections::HashMap,
+    path::Path,
     sync::{
-        mpsc::{channel, Sender},
-        Arc, Mutex, Once,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Mutex,
     },
     thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
-use cfg_if::cfg_if;
+use crate::{
+    log_capture::try_capture,
+    log_config::LogConfig,
+    log_level::LogLevel,
+    logger_impl::{LogMessage, Logger, APP_LOGGER_NAME, CORE_LOGGER_NAME},
+};
 
-use crate::logger_impl::{LogMessage, Logger, APP_LOGGER_NAME, CORE_LOGGER_NAME};
+pub static LOGGER_THREAD_SENDER: Mutex<Option<Sender<LogMessage>>> = Mutex::new(None);
 
-pub static mut LOGGER_THREAD_SENDER: Option<Arc<Mutex<Sender<LogMessage>>>> = None;
+/// How long the logger thread holds back the "... repeated N times" records
+/// of deduplicating loggers
+pub const DEDUP_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 
-cfg_if! {
-    if #[cfg(debug_assertions)] {
-        static mut LOGGER_THREAD_INIT_ONCE: Once = Once::new();
-        static mut LOGGER_THREAD_JOIN_ONCE: Once = Once::new();
-    }
+/// Index into [`LogLevel::ALL`]
+static GLOBAL_MIN_LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+/// Messages below this level are dropped before they reach any logger, on
+/// top of the min level of each logger. Everything passes by default.
+pub fn set_global_min_level(level: LogLevel) {
+    GLOBAL_MIN_LEVEL.store(level as usize, Ordering::Relaxed);
 }
 
-static mut LOGGER_THREAD_HANDLE: Option<std::thread::JoinHandle<()>> = None;
+pub fn global_min_level() -> LogLevel {
+    LogLevel::ALL[GLOBAL_MIN_LEVEL.load(Ordering::Relaxed)].clone()
+}
 
+/// Starts the logger thread.
+///
+/// # Panics
+///
+/// Panics if the logger thread is already running
 pub fn logging_thread_start(loggers: Option<Vec<Logger>>) -> JoinHandle<()> {
-    cfg_if! {
-        if #[cfg(debug_assertions)] {
-            unsafe {
-                if LOGGER_THREAD_INIT_ONCE.is_completed() {
-                    panic!("logging_thread_start is called more than once");
-                }
-                LOGGER_THREAD_INIT_ONCE.call_once(|| {});
-            }
-        }
-    };
+    try_logging_thread_start(loggers).expect("logging_thread_start is called more than once")
+}
 
-    let (sender, receiver) = channel::<LogMessage>();
+pub fn logging_thread_running() -> bool {
+    LOGGER_THREAD_SENDER
+        .lock()
+        .expect("Failed to lock the logger thread sender")
+        .is_some()
+}
 
-    unsafe {
-        LOGGER_THREAD_SENDER = Some(Arc::new(Mutex::new(sender)));
+/// Starts the logger thread unless it's already running, in which case the
+/// loggers are dropped and `None` is returned. Several apps built in one
+/// process share the first logger thread this way.
+pub fn try_logging_thread_start(loggers: Option<Vec<Logger>>) -> Option<JoinHandle<()>> {
+    let mut thread_sender = LOGGER_THREAD_SENDER
+        .lock()
+        .expect("Failed to lock the logger thread sender");
+
+    if thread_sender.is_some() {
+        return None;
     }
 
+    let (sender, receiver) = channel::<LogMessage>();
+
+    *thread_sender = Some(sender);
+
     let mut logger_map = match loggers {
         Some(loggers) => {
-            let mut logger_map = std::collections::HashMap::new();
+            let mut logger_map = HashMap::new();
 
             for logger in loggers {
                 debug_assert!(
@@ -53,7 +82,7 @@ pub fn logging_thread_start(loggers: Option<Vec<Logger>>) -> JoinHandle<()> {
             }
             logger_map
         }
-        None => std::collections::HashMap::new(),
+        None => HashMap::new(),
     };
 
     if logger_map.get(CORE_LOGGER_NAME).is_none() {
@@ -66,53 +95,119 @@ pub fn logging_thread_start(loggers: Option<Vec<Logger>>) -> JoinHandle<()> {
 
     let logger_map = logger_map;
 
-    std::thread::spawn(move || loop {
-        let msg = receiver.recv().unwrap();
+    Some(std::thread::spawn(move || {
+        let mut last_flush = Instant::now();
+
+        loop {
+            let msg = match receiver.recv_timeout(DEDUP_FLUSH_INTERVAL) {
+                Ok(msg) => msg,
+                Err(RecvTimeoutError::Timeout) => {
+                    flush_loggers(&logger_map, &mut last_flush);
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    flush_loggers(&logger_map, &mut last_flush);
+                    break;
+                }
+            };
 
-        let logger = match logger_map.get(msg.logger_name) {
-            Some(logger) => logger,
-            None => {
-                eprintln!("Logger with name \"{}\" does not exist", msg.logger_name);
-                continue;
+            match logger_map.get(msg.logger_name) {
+                Some(logger) => logger.log(msg.level, msg.msg),
+                None => eprintln!("Logger with name \"{}\" does not exist", msg.logger_name),
             }
-        };
 
-        logger.log(msg.level, msg.msg);
+            if msg.shutdown && msg.logger_name == "core" {
+                flush_loggers(&logger_map, &mut last_flush);
+                break;
+            }
 
-        if msg.shutdown && msg.logger_name == "core" {
-            break;
+            if last_flush.elapsed() >= DEDUP_FLUSH_INTERVAL {
+                flush_loggers(&logger_map, &mut last_flush);
+            }
         }
-    })
+    }))
 }
 
-pub fn logging_thread_join() {
-    cfg_if! {
-        if #[cfg(debug_assertions)] {
-            unsafe {
-                if LOGGER_THREAD_JOIN_ONCE.is_completed() {
-                    panic!("logging_thread_join is called more than once");
-                }
-                LOGGER_THREAD_JOIN_ONCE.call_once(|| {});
-            }
-        }
+fn flush_loggers(loggers: &HashMap<&'static str, Logger>, last_flush: &mut Instant) {
+    for logger in loggers.values() {
+        logger.flush();
+    }
+    *last_flush = Instant::now();
+}
+
+/// Sends the message to the logger thread. Captured messages (see
+/// [`crate::log_capture::capture_logs`]) stay on the calling thread, and
+/// messages sent while the logger thread isn't running go straight to stderr.
+pub fn log_message(message: LogMessage) {
+    if !message.shutdown && message.level < global_min_level() {
+        return;
+    }
+
+    if try_capture(&message) {
+        return;
+    }
+
+    let result = match LOGGER_THREAD_SENDER
+        .lock()
+        .expect("Failed to lock the logger thread sender")
+        .as_ref()
+    {
+        Some(sender) => sender.send(message),
+        None => Err(std::sync::mpsc::SendError(message)),
     };
 
-    unsafe {
-        LOGGER_THREAD_SENDER
-            .as_ref()
-            .unwrap()
-            .lock()
-            .expect("Failed to lock the logger thread sender")
-            .send(LogMessage {
-                logger_name: CORE_LOGGER_NAME,
-                level: crate::LogLevel::Info,
-                msg: "Shutting down the logger thread".into(),
-                shutdown: true,
-            })
-            .unwrap();
+    if let Err(std::sync::mpsc::SendError(message)) = result {
+        eprintln!(
+            "{} [{}]: {}",
+            message.logger_name, message.level, message.msg
+        );
     }
+}
 
-    unsafe {
-        LOGGER_THREAD_HANDLE = None;
+/// Starts the logger thread with the loggers described in the config file.
+/// Falls back to the default loggers if the file can't be loaded.
+///
+/// # Panics
+///
+/// Panics if the logger thread is already running
+pub fn logging_thread_start_with_config(path: &Path) -> JoinHandle<()> {
+    try_logging_thread_start_with_config(path)
+        .expect("logging_thread_start_with_config is called more than once")
+}
+
+/// Same as [`logging_thread_start_with_config`], but does nothing if the
+/// logger thread is already running
+pub fn try_logging_thread_start_with_config(path: &Path) -> Option<JoinHandle<()>> {
+    if logging_thread_running() {
+        return None;
+    }
+
+    let loggers = match LogConfig::load(path) {
+        Ok(config) => Some(config.into_loggers()),
+        Err(err) => {
+            eprintln!("Failed to load the log config, using the default loggers: {err}");
+            None
+        }
+    };
+
+    try_logging_thread_start(loggers)
+}
+
+/// Flushes and stops the logger thread. Messages logged afterwards go to
+/// stderr until the thread is started again. Does nothing if the thread
+/// isn't running.
+pub fn logging_thread_join() {
+    let sender = LOGGER_THREAD_SENDER
+        .lock()
+        .expect("Failed to lock the logger thread sender")
+        .take();
+
+    if let Some(sender) = sender {
+        let _ = sender.send(LogMessage {
+            logger_name: CORE_LOGGER_NAME,
+            level: crate::LogLevel::Info,
+            msg: "Shutting down the logger thread".into(),
+            shutdown: true,
+        });
     }
 }
@@ -1,11 +1,16 @@
 #![feature(macro_metavar_expr)]
 #![feature(let_chains)]
 
+pub mod console;
 pub mod global_loggers;
+pub mod log_capture;
+pub mod log_config;
 pub mod log_errors;
 pub mod log_level;
 pub mod log_target;
 pub mod logger_impl;
+pub mod rate_limit;
+pub mod span;
 pub mod terminal_escape_code;
 pub mod terminal_macros;
 
@@ -0,0 +1,133 @@
+use std::cell::RefCell;
+
+use crate::{log_level::LogLevel, logger_impl::LogMessage};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub logger_name: &'static str,
+    pub level: LogLevel,
+    pub msg: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CapturedLogs {
+    records: Vec<LogRecord>,
+}
+
+impl CapturedLogs {
+    pub fn records(&self) -> &[LogRecord] {
+        &self.records
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LogRecord> {
+        self.records.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn with_level(&self, level: LogLevel) -> impl Iterator<Item = &LogRecord> {
+        self.records.iter().filter(move |r| r.level == level)
+    }
+
+    /// Checks if there is a record with the given level containing `needle`
+    pub fn contains(&self, level: LogLevel, needle: &str) -> bool {
+        self.with_level(level).any(|r| r.msg.contains(needle))
+    }
+}
+
+thread_local! {
+    static CAPTURE_STACK: RefCell<Vec<Vec<LogRecord>>> = const { RefCell::new(Vec::new()) };
+}
+
+struct CaptureGuard;
+
+impl CaptureGuard {
+    fn push() -> Self {
+        CAPTURE_STACK.with(|stack| stack.borrow_mut().push(Vec::new()));
+        Self
+    }
+}
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        CAPTURE_STACK.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+/// Runs `f` and returns everything it logged on the current thread instead of
+/// sending it to the logger thread. The logger thread doesn't have to be
+/// started. Meant for tests.
+///
+/// Records from systems dispatched on other threads are not captured, run
+/// them with `RunNow` to assert on their output.
+pub fn capture_logs<F: FnOnce()>(f: F) -> CapturedLogs {
+    let guard = CaptureGuard::push();
+    f();
+    let records = CAPTURE_STACK.with(|stack| {
+        stack
+            .borrow_mut()
+            .last_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    });
+    drop(guard);
+
+    CapturedLogs { records }
+}
+
+/// Stores the message if a capture is active on this thread
+pub(crate) fn try_capture(message: &LogMessage) -> bool {
+    CAPTURE_STACK.with(|stack| match stack.borrow_mut().last_mut() {
+        Some(records) => {
+            records.push(LogRecord {
+                logger_name: message.logger_name,
+                level: message.level.clone(),
+                msg: message.msg.clone(),
+            });
+            true
+        }
+        None => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{log_to_global, LogLevel};
+
+    use super::capture_logs;
+
+    #[test]
+    fn captures_records_synchronously() {
+        let logs = capture_logs(|| {
+            log_to_global!("core", LogLevel::Info, "Loaded {} meshes", 3);
+            log_to_global!("core", LogLevel::Error, "Mesh with handle {} not found", 42);
+            log_to_global!("app", LogLevel::Info, "from the app");
+        });
+
+        assert_eq!(logs.len(), 3);
+        assert!(logs.contains(LogLevel::Error, "not found"));
+        assert!(logs.contains(LogLevel::Info, "Loaded 3 meshes"));
+        assert_eq!(logs.records()[2].logger_name, "app");
+    }
+
+    #[test]
+    fn nested_captures_are_separate() {
+        let mut inner = None;
+        let outer = capture_logs(|| {
+            log_to_global!("core", LogLevel::Info, "outer");
+            inner = Some(capture_logs(|| {
+                log_to_global!("core", LogLevel::Info, "inner")
+            }));
+        });
+
+        assert_eq!(outer.len(), 1);
+        assert!(outer.contains(LogLevel::Info, "outer"));
+        assert!(inner.unwrap().contains(LogLevel::Info, "inner"));
+    }
+}
@@ -0,0 +1,231 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    console::ColorMode,
+    log_errors::LogError,
+    log_level::LogLevel,
+    log_target::{file_target, LogTarget},
+    logger_impl::{FileRotation, Logger},
+};
+
+/// Set of loggers described in a `logging.toml` or `logging.ron` file.
+///
+/// ```toml
+/// [[loggers]]
+/// name = "core"
+/// label = "Engine"
+/// min_level = "Info"
+/// dedup = true
+/// color = "Auto"
+/// format = "{time} {label} [{level}]: {msg}"
+/// targets = ["Stdout", "Stderr", { File = { path = "log/core_{timestamp}.log" } }]
+/// rotation = { max_size_bytes = 1048576, max_files = 3 }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct LogConfig {
+    #[serde(default)]
+    pub loggers: Vec<LoggerConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoggerConfig {
+    pub name: String,
+    pub label: Option<String>,
+    #[serde(default = "default_min_level")]
+    pub min_level: LogLevel,
+    #[serde(default)]
+    pub targets: Vec<TargetConfig>,
+    #[serde(default)]
+    pub dedup: bool,
+    #[serde(default)]
+    pub color: ColorMode,
+    #[serde(default = "default_split_stderr")]
+    pub split_stderr: bool,
+    #[serde(default = "default_stderr_threshold")]
+    pub stderr_threshold: LogLevel,
+    pub rotation: Option<FileRotation>,
+    /// Record layout, see [`crate::logger_impl::DEFAULT_LOG_FORMAT`]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum TargetConfig {
+    Stdout,
+    Stderr,
+    /// `{timestamp}` in the path is replaced with the logger start time
+    File {
+        path: String,
+    },
+}
+
+fn default_min_level() -> LogLevel {
+    LogLevel::Debug
+}
+
+fn default_split_stderr() -> bool {
+    true
+}
+
+fn default_stderr_threshold() -> LogLevel {
+    LogLevel::Error
+}
+
+impl LogConfig {
+    pub fn load(path: &Path) -> Result<Self, LogError> {
+        let path_str = path.to_string_lossy().to_string();
+
+        let source = std::fs::read_to_string(path).map_err(|err| LogError::CouldNotOpenFile {
+            path: path_str.clone(),
+            source: err.into(),
+        })?;
+
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&source),
+            Some("ron") => Self::from_ron_str(&source),
+            _ => return Err(LogError::UnsupportedConfigFormat(path_str)),
+        };
+
+        parsed.map_err(|source| LogError::CouldNotParseConfig {
+            path: path_str,
+            source,
+        })
+    }
+
+    pub fn from_toml_str(source: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn from_ron_str(source: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(source)?)
+    }
+
+    /// Builds the described loggers. Logger names and labels live for the
+    /// rest of the program, so this is meant to be called once at startup.
+    pub fn into_loggers(self) -> Vec<Logger> {
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H:%M:%S").to_string();
+
+        self.loggers
+            .into_iter()
+            .map(|config| config.into_logger(&timestamp))
+            .collect()
+    }
+}
+
+impl LoggerConfig {
+    fn into_logger(self, timestamp: &str) -> Logger {
+        let name: &'static str = Box::leak(self.name.into_boxed_str());
+        let label: &'static str = match self.label {
+            Some(label) => Box::leak(label.into_boxed_str()),
+            None => name,
+        };
+
+        let targets = self
+            .targets
+            .into_iter()
+            .map(|target| match target {
+                TargetConfig::Stdout => LogTarget::Stdout,
+                TargetConfig::Stderr => LogTarget::Stderr,
+                TargetConfig::File { path } => {
+                    file_target(&path.replace("{timestamp}", timestamp), None)
+                }
+            })
+            .collect();
+
+        let logger = match self.format {
+            Some(format) => Logger::new(self.min_level, label, name, targets).with_format(format),
+            None => Logger::new(self.min_level, label, name, targets),
+        };
+
+        logger
+            .with_dedup(self.dedup)
+            .with_color_mode(self.color)
+            .with_stderr_threshold(self.split_stderr.then_some(self.stderr_threshold))
+            .with_rotation(self.rotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{console::ColorMode, log_level::LogLevel, logger_impl::FileRotation};
+
+    use super::{LogConfig, TargetConfig};
+
+    #[test]
+    fn parses_toml() {
+        let config = LogConfig::from_toml_str(
+            r#"
+            [[loggers]]
+            name = "core"
+            label = "Engine"
+            min_level = "Warn"
+            dedup = true
+            color = "Never"
+            targets = ["Stdout", { File = { path = "log/core.log" } }]
+            rotation = { max_size_bytes = 1024, max_files = 2 }
+            format = "{level} {msg}"
+
+            [[loggers]]
+            name = "app"
+            split_stderr = false
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.loggers.len(), 2);
+
+        let core = &config.loggers[0];
+        assert_eq!(core.label.as_deref(), Some("Engine"));
+        assert_eq!(core.min_level, LogLevel::Warn);
+        assert!(core.dedup);
+        assert_eq!(core.color, ColorMode::Never);
+        assert_eq!(
+            core.targets,
+            vec![
+                TargetConfig::Stdout,
+                TargetConfig::File {
+                    path: "log/core.log".into()
+                }
+            ]
+        );
+        assert_eq!(
+            core.rotation,
+            Some(FileRotation {
+                max_size_bytes: 1024,
+                max_files: 2
+            })
+        );
+
+        assert_eq!(core.format.as_deref(), Some("{level} {msg}"));
+
+        let app = &config.loggers[1];
+        assert_eq!(app.min_level, LogLevel::Debug);
+        assert!(!app.split_stderr);
+        assert!(app.targets.is_empty());
+        assert!(app.format.is_none());
+    }
+
+    #[test]
+    fn parses_ron() {
+        let config = LogConfig::from_ron_str(
+            r#"(
+                loggers: [
+                    (
+                        name: "core",
+                        min_level: Info,
+                        targets: [Stdout, Stderr],
+                    ),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.loggers.len(), 1);
+        assert_eq!(config.loggers[0].min_level, LogLevel::Info);
+        assert_eq!(
+            config.loggers[0].targets,
+            vec![TargetConfig::Stdout, TargetConfig::Stderr]
+        );
+    }
+}
@@ -10,4 +10,13 @@ pub enum LogError {
 
     #[error("could not print to file '{path}': {source}")]
     CouldNotPrintToFile { path: String, source: anyhow::Error },
+
+    #[error("could not rotate the log file '{path}': {source}")]
+    CouldNotRotateFile { path: String, source: anyhow::Error },
+
+    #[error("could not parse the log config '{path}': {source}")]
+    CouldNotParseConfig { path: String, source: anyhow::Error },
+
+    #[error("unsupported log config format '{0}', expected .toml or .ron")]
+    UnsupportedConfigFormat(String),
 }
@@ -1,9 +1,17 @@
-use std::{cell::RefCell, fs::OpenOptions, io::Write};
+use std::{
+    cell::{Cell, RefCell},
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+};
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    console::{ColorMode, ColorTheme, ConsoleStream},
     escape_sequence,
+    log_errors::LogError,
     log_level::LogLevel,
     log_target::{file_target, LogTarget},
     TerminalEscapeSequence, RESET,
@@ -22,11 +30,33 @@ pub struct Logger {
     label: &'static str,
     name: &'static str,
     targets: Vec<LogTarget>,
+    dedup: bool,
+    last_record: RefCell<Option<(LogLevel, String)>>,
+    repeat_count: Cell<usize>,
+    theme: ColorTheme,
+    stdout_colored: bool,
+    stderr_colored: bool,
+    stderr_threshold: Option<LogLevel>,
+    rotation: Option<FileRotation>,
+    format: String,
+}
+
+/// Size based rotation for file targets. When a log file grows past
+/// `max_size_bytes` it is renamed to `<path>.1`, older files are shifted up
+/// and anything past `max_files` is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileRotation {
+    pub max_size_bytes: u64,
+    pub max_files: usize,
 }
 
 pub const CORE_LOGGER_NAME: &str = "core";
 pub const APP_LOGGER_NAME: &str = "app";
 
+/// Record layout used unless [`Logger::with_format`] sets another one.
+/// `{label}`, `{name}`, `{level}`, `{time}` and `{msg}` are replaced in it.
+pub const DEFAULT_LOG_FORMAT: &str = "{label} [{level}]: {msg}";
+
 impl Default for Logger {
     fn default() -> Self {
         Self::new(
@@ -110,42 +140,149 @@ impl Logger {
             label,
             name,
             targets: targets.collect(),
+            dedup: false,
+            last_record: RefCell::new(None),
+            repeat_count: Cell::new(0),
+            theme: ColorTheme::default(),
+            stdout_colored: ColorMode::Auto.colors_enabled(ConsoleStream::Stdout),
+            stderr_colored: ColorMode::Auto.colors_enabled(ConsoleStream::Stderr),
+            stderr_threshold: Some(LogLevel::Error),
+            rotation: None,
+            format: DEFAULT_LOG_FORMAT.into(),
         }
     }
 
+    pub fn with_rotation(mut self, rotation: Option<FileRotation>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// See [`DEFAULT_LOG_FORMAT`] for the placeholders
+    pub fn with_format(mut self, format: impl Into<String>) -> Self {
+        self.format = format.into();
+        self
+    }
+
+    pub fn with_color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.stdout_colored = color_mode.colors_enabled(ConsoleStream::Stdout);
+        self.stderr_colored = color_mode.colors_enabled(ConsoleStream::Stderr);
+        self
+    }
+
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Records at or above `threshold` go to [`LogTarget::Stderr`], the rest
+    /// go to [`LogTarget::Stdout`]. With `None` every console target gets
+    /// every record.
+    pub fn with_stderr_threshold(mut self, threshold: Option<LogLevel>) -> Self {
+        self.stderr_threshold = threshold;
+        self
+    }
+
+    /// Collapse identical consecutive messages into a single
+    /// "... repeated N times" record
+    pub fn with_dedup(mut self, dedup: bool) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
     pub fn log(&self, level: LogLevel, msg: String) {
         if level < self.min_level {
             return;
         }
 
+        if self.dedup {
+            let mut last_record = self.last_record.borrow_mut();
+
+            let is_repeat = matches!(
+                last_record.as_ref(),
+                Some((last_level, last_msg)) if *last_level == level && *last_msg == msg
+            );
+
+            if is_repeat {
+                self.repeat_count.set(self.repeat_count.get() + 1);
+                return;
+            }
+
+            if let Some((last_level, _)) = last_record.as_ref() {
+                self.log_repeats(last_level);
+            }
+
+            *last_record = Some((level.clone(), msg.clone()));
+        }
+
+        self.log_to_targets(&level, &msg);
+    }
+
+    /// Writes out the pending "... repeated N times" record, if there is one.
+    /// The logger thread calls it every [`crate::global_loggers::DEDUP_FLUSH_INTERVAL`].
+    pub fn flush(&self) {
+        if let Some((last_level, _)) = self.last_record.borrow_mut().take() {
+            self.log_repeats(&last_level);
+        }
+    }
+
+    fn log_repeats(&self, level: &LogLevel) {
+        let repeats = self.repeat_count.replace(0);
+        if repeats > 0 {
+            self.log_to_targets(level, &format!("... repeated {repeats} times"));
+        }
+    }
+
+    fn log_to_targets(&self, level: &LogLevel, msg: &str) {
         for target in self.targets.iter() {
-            if let Err(e) = self.log_to_target(target, &level, &msg) {
+            if let Err(e) = self.log_to_target(target, level, msg) {
                 eprintln!("{e}");
             }
         }
     }
 
+    fn format_record(&self, level: &LogLevel, msg: &str) -> String {
+        let mut record = self
+            .format
+            .replace("{label}", self.label)
+            .replace("{name}", self.name)
+            .replace("{level}", &level.to_string());
+        if record.contains("{time}") {
+            let time = chrono::Local::now().format("%H:%M:%S%.3f").to_string();
+            record = record.replace("{time}", &time);
+        }
+        // The message goes last so placeholders in it are left alone
+        record.replace("{msg}", msg)
+    }
+
     fn log_to_target(
         &self,
         target: &LogTarget,
         level: &LogLevel,
-        msg: &String,
+        msg: &str,
     ) -> Result<(), anyhow::Error> {
-        if (target != &LogTarget::Stderr && level >= &LogLevel::Error)
-            || (target == &LogTarget::Stderr && level < &LogLevel::Error)
-        {
-            return Ok(());
-        }
         match target {
             LogTarget::Stderr | LogTarget::Stdout => {
-                let msg = format!(
-                    "{}{} [{}]: {}{}",
-                    TerminalEscapeSequence::from(level),
-                    self.label,
-                    level,
-                    msg,
-                    escape_sequence!(RESET)
-                );
+                let (to_stderr, colored) = match target {
+                    LogTarget::Stderr => (true, self.stderr_colored),
+                    _ => (false, self.stdout_colored),
+                };
+
+                if let Some(threshold) = &self.stderr_threshold {
+                    if to_stderr != (level >= threshold) {
+                        return Ok(());
+                    }
+                }
+
+                let msg = if colored {
+                    format!(
+                        "{}{}{}",
+                        self.theme.get(level),
+                        self.format_record(level, msg),
+                        escape_sequence!(RESET)
+                    )
+                } else {
+                    self.format_record(level, msg)
+                };
                 if target == &LogTarget::Stdout {
                     println!("{msg}");
                     Ok(())
@@ -154,11 +291,164 @@ impl Logger {
                     Ok(())
                 }
             }
-            LogTarget::File(_, Some(file)) => {
-                let msg = format!("{} [{}]: {}\n", self.label, level, msg);
-                Ok(file.try_borrow_mut()?.write(msg.as_bytes()).map(|_| ())?)
+            LogTarget::File(path, Some(file)) => {
+                let msg = format!("{}\n", self.format_record(level, msg));
+                let mut file = file.try_borrow_mut()?;
+                file.write_all(msg.as_bytes())?;
+
+                if let Some(rotation) = &self.rotation {
+                    if file.metadata()?.len() >= rotation.max_size_bytes {
+                        *file = rotate_file(path, rotation).map_err(|source| {
+                            LogError::CouldNotRotateFile {
+                                path: path.to_string(),
+                                source,
+                            }
+                        })?;
+                    }
+                }
+
+                Ok(())
             }
             _ => panic!("LogTarget::File(_, None) is not allowed after the logger initialization"),
         }
     }
 }
+
+fn rotate_file(path: &str, rotation: &FileRotation) -> Result<File> {
+    let rotated_path = |index: usize| format!("{path}.{index}");
+
+    if rotation.max_files == 0 {
+        std::fs::remove_file(path)?;
+    } else {
+        let oldest = rotated_path(rotation.max_files);
+        if Path::new(&oldest).exists() {
+            std::fs::remove_file(oldest)?;
+        }
+        for index in (1..rotation.max_files).rev() {
+            let from = rotated_path(index);
+            if Path::new(&from).exists() {
+                std::fs::rename(from, rotated_path(index + 1))?;
+            }
+        }
+        std::fs::rename(path, rotated_path(1))?;
+    }
+
+    Ok(OpenOptions::new().append(true).create(true).open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Fresh path in the temp dir, removed with its rotated files on drop
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "bizarre_logger_{}_{}.log",
+                name,
+                std::process::id()
+            ));
+            let log = Self(path);
+            log.remove();
+            log
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+
+        fn rotated(&self, index: usize) -> PathBuf {
+            PathBuf::from(format!("{}.{}", self.path(), index))
+        }
+
+        fn target(&self) -> LogTarget {
+            let file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&self.0)
+                .unwrap();
+            file_target(self.path(), Some(file))
+        }
+
+        fn lines(&self) -> Vec<String> {
+            std::fs::read_to_string(&self.0)
+                .unwrap()
+                .lines()
+                .map(String::from)
+                .collect()
+        }
+
+        fn remove(&self) {
+            let _ = std::fs::remove_file(&self.0);
+            for index in 1..10 {
+                let _ = std::fs::remove_file(self.rotated(index));
+            }
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            self.remove();
+        }
+    }
+
+    #[test]
+    fn dedup_collapses_repeats() {
+        let log = TempLog::new("dedup");
+        let logger =
+            Logger::new(LogLevel::Debug, "Test", "test", vec![log.target()]).with_dedup(true);
+
+        for _ in 0..4 {
+            logger.log(LogLevel::Warn, "same".into());
+        }
+        logger.log(LogLevel::Warn, "other".into());
+        logger.log(LogLevel::Warn, "other".into());
+        logger.flush();
+
+        assert_eq!(
+            log.lines(),
+            [
+                "Test [WARN]: same",
+                "Test [WARN]: ... repeated 3 times",
+                "Test [WARN]: other",
+                "Test [WARN]: ... repeated 1 times",
+            ]
+        );
+    }
+
+    #[test]
+    fn rotation_shifts_files_up_to_max_files() {
+        let log = TempLog::new("rotation");
+        let rotation = FileRotation {
+            max_size_bytes: 16,
+            max_files: 2,
+        };
+        let logger = Logger::new(LogLevel::Debug, "Test", "test", vec![log.target()])
+            .with_rotation(Some(rotation));
+
+        for index in 1..=4 {
+            logger.log(LogLevel::Info, format!("message {index}"));
+        }
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(log.rotated(1)), "Test [INFO]: message 4\n");
+        assert_eq!(read(log.rotated(2)), "Test [INFO]: message 3\n");
+        assert!(!log.rotated(3).exists());
+        assert_eq!(read(log.0.clone()), "");
+    }
+
+    #[test]
+    fn formats_records_with_the_pattern() {
+        let log = TempLog::new("format");
+        let logger = Logger::new(LogLevel::Debug, "Test", "test", vec![log.target()])
+            .with_format("<{level}> {name}: {msg}");
+
+        logger.log(LogLevel::Info, "hello {label}".into());
+
+        assert_eq!(log.lines(), ["<INFO> test: hello {label}"]);
+    }
+}
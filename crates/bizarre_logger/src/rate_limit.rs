@@ -0,0 +1,66 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Per call site limiter used by the `*_every!` log macros.
+pub struct RateLimiter {
+    last: Mutex<Option<Instant>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    pub const fn new() -> Self {
+        Self {
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Returns `true` if at least `period` has passed since the last time
+    /// this function returned `true`
+    pub fn should_log(&self, period: Duration) -> bool {
+        let now = Instant::now();
+        let mut last = self.last.lock().expect("Failed to lock the rate limiter");
+
+        match *last {
+            Some(last) if now.duration_since(last) < period => false,
+            _ => {
+                *last = Some(now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RateLimiter;
+
+    #[test]
+    fn first_call_always_logs() {
+        let limiter = RateLimiter::new();
+        assert!(limiter.should_log(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn calls_inside_period_are_suppressed() {
+        let limiter = RateLimiter::new();
+        assert!(limiter.should_log(Duration::from_secs(3600)));
+        assert!(!limiter.should_log(Duration::from_secs(3600)));
+        assert!(!limiter.should_log(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn calls_after_period_are_logged() {
+        let limiter = RateLimiter::new();
+        assert!(limiter.should_log(Duration::ZERO));
+        assert!(limiter.should_log(Duration::ZERO));
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    cell::Cell,
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{log_to_global, LogLevel};
+
+/// What a [`Span`] does when it closes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanReport {
+    /// Emit a debug record with the elapsed time and add it to the stats
+    Record,
+    /// Only add the elapsed time to the stats. Use it for per-frame spans
+    Aggregate,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpanStats {
+    pub count: u64,
+    pub total: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl SpanStats {
+    fn new(elapsed: Duration) -> Self {
+        Self {
+            count: 1,
+            total: elapsed,
+            min: elapsed,
+            max: elapsed,
+        }
+    }
+
+    fn add(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.min = self.min.min(elapsed);
+        self.max = self.max.max(elapsed);
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.count as f64)
+        }
+    }
+}
+
+thread_local! {
+    static SPAN_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+static SPAN_STATS: Mutex<BTreeMap<&'static str, SpanStats>> = Mutex::new(BTreeMap::new());
+
+/// Scoped timing guard. Created by the `core_span!`/`span!` macros, closes
+/// when dropped. Spans nest per thread.
+#[must_use = "the span closes as soon as it is dropped"]
+pub struct Span {
+    logger_name: &'static str,
+    name: &'static str,
+    report: SpanReport,
+    depth: usize,
+    start: Instant,
+}
+
+impl Span {
+    pub fn enter(logger_name: &'static str, name: &'static str, report: SpanReport) -> Self {
+        let depth = SPAN_DEPTH.with(|depth| {
+            let current = depth.get();
+            depth.set(current + 1);
+            current
+        });
+
+        Self {
+            logger_name,
+            name,
+            report,
+            depth,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Nesting level of the span on its thread, 0 for the outermost one
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed = self.elapsed();
+
+        SPAN_DEPTH.with(|depth| depth.set(self.depth));
+
+        if let Ok(mut stats) = SPAN_STATS.lock() {
+            stats
+                .entry(self.name)
+                .and_modify(|s| s.add(elapsed))
+                .or_insert_with(|| SpanStats::new(elapsed));
+        }
+
+        if self.report == SpanReport::Record {
+            log_to_global!(
+                self.logger_name,
+                LogLevel::Debug,
+                "{}span \"{}\" took {:.3} ms",
+                "  ".repeat(self.depth),
+                self.name,
+                elapsed.as_secs_f64() * 1000.0
+            );
+        }
+    }
+}
+
+/// Snapshot of the stats of every span closed so far
+pub fn span_stats() -> Vec<(&'static str, SpanStats)> {
+    SPAN_STATS
+        .lock()
+        .map(|stats| stats.iter().map(|(name, s)| (*name, *s)).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the collected span stats and resets them
+pub fn take_span_stats() -> Vec<(&'static str, SpanStats)> {
+    SPAN_STATS
+        .lock()
+        .map(|mut stats| std::mem::take(&mut *stats).into_iter().collect())
+        .unwrap_or_default()
+}
+
+/// Writes a summary of the collected span stats into the given logger
+pub fn log_span_stats(logger_name: &'static str) {
+    for (name, stats) in span_stats() {
+        log_to_global!(
+            logger_name,
+            LogLevel::Info,
+            "span \"{}\": {} calls, avg {:.3} ms, min {:.3} ms, max {:.3} ms",
+            name,
+            stats.count,
+            stats.average().as_secs_f64() * 1000.0,
+            stats.min.as_secs_f64() * 1000.0,
+            stats.max.as_secs_f64() * 1000.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Span, SpanReport};
+
+    #[test]
+    fn spans_nest_per_thread() {
+        let outer = Span::enter("core", "outer", SpanReport::Aggregate);
+        {
+            let inner = Span::enter("core", "inner", SpanReport::Aggregate);
+            assert_eq!(inner.depth(), outer.depth() + 1);
+        }
+        let sibling = Span::enter("core", "sibling", SpanReport::Aggregate);
+        assert_eq!(sibling.depth(), outer.depth() + 1);
+
+        std::thread::spawn(|| {
+            let other = Span::enter("core", "other_thread", SpanReport::Aggregate);
+            assert_eq!(other.depth(), 0);
+        })
+        .join()
+        .unwrap();
+    }
+}
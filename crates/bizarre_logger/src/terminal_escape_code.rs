@@ -38,6 +38,7 @@ pub const fn bg_color(color: TerminalEscapeCode) -> TerminalEscapeCode {
     color + 10
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TerminalEscapeSequence(pub Vec<TerminalEscapeCode>);
 
 impl Display for TerminalEscapeSequence {
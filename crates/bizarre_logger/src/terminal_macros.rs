@@ -8,21 +8,12 @@ macro_rules! escape_sequence {
 #[macro_export]
 macro_rules! log_to_global {
     ($logger_name: expr, $log_level: expr, $msg: expr) => {{
-        unsafe {
-            $crate::global_loggers::LOGGER_THREAD_SENDER
-                .as_ref()
-                .expect("There is no logger thread sender. Make sure that loggin_thread_start() is called before the first attempt to write into a logger")
-                .lock()
-                .expect("Failed to lock the logger sender")
-                .send(
-                    $crate::logger_impl::LogMessage {
-                        logger_name: $logger_name,
-                        level: $log_level,
-                        msg: $msg.to_string(),
-                        shutdown: false,
-                    },
-                ).expect("Failed to send log message to global logger");
-        }
+        $crate::global_loggers::log_message($crate::logger_impl::LogMessage {
+            logger_name: $logger_name,
+            level: $log_level,
+            msg: $msg.to_string(),
+            shutdown: false,
+        });
     }};
     ($logger_name: expr, $log_level: expr, $msg:literal, $($args: expr),+) => {{
         let msg = format!($msg, $($args),+);
@@ -64,4 +55,76 @@ gen_log_macros!(
     }
 );
 
+macro_rules! _gen_rate_limited_log_macro_inner {
+    ($logger_name: tt, $once_name: tt, $every_name: tt, $log_level_name: tt) => {
+        #[macro_export]
+        macro_rules! $once_name {
+            ($$($$args: expr),+) => {{
+                static ONCE: ::std::sync::Once = ::std::sync::Once::new();
+                ONCE.call_once(|| {
+                    $crate::log_to_global!(stringify!($logger_name), $crate::LogLevel::$log_level_name, $$($$args),+)
+                });
+            }}
+        }
+
+        #[macro_export]
+        macro_rules! $every_name {
+            ($$period: expr, $$($$args: expr),+) => {{
+                static LIMITER: $crate::rate_limit::RateLimiter = $crate::rate_limit::RateLimiter::new();
+                if LIMITER.should_log($$period) {
+                    $crate::log_to_global!(stringify!($logger_name), $crate::LogLevel::$log_level_name, $$($$args),+)
+                }
+            }}
+        }
+    }
+}
+
+macro_rules! gen_rate_limited_log_macros {
+    ($($logger_name: tt { $($once_name: tt, $every_name: tt => $log_level: tt);+; })+) => {
+        $($(_gen_rate_limited_log_macro_inner!($logger_name, $once_name, $every_name, $log_level);)+)+
+    };
+}
+
+gen_rate_limited_log_macros!(
+    core {
+        core_debug_once, core_debug_every => Debug;
+        core_info_once, core_info_every => Info;
+        core_warn_once, core_warn_every => Warn;
+        core_error_once, core_error_every => Error;
+        core_critical_once, core_critical_every => Critical;
+    }
+    app {
+        debug_once, debug_every => Debug;
+        info_once, info_every => Info;
+        warn_once, warn_every => Warn;
+        error_once, error_every => Error;
+        critical_once, critical_every => Critical;
+    }
+);
+
+macro_rules! _gen_span_macro_inner {
+    ($logger_name: tt, $macro_name: tt) => {
+        #[macro_export]
+        macro_rules! $macro_name {
+            ($$name: expr) => {
+                $crate::span::Span::enter(
+                    stringify!($logger_name),
+                    $$name,
+                    $crate::span::SpanReport::Record,
+                )
+            };
+            ($$name: expr, aggregate) => {
+                $crate::span::Span::enter(
+                    stringify!($logger_name),
+                    $$name,
+                    $crate::span::SpanReport::Aggregate,
+                )
+            };
+        }
+    };
+}
+
+_gen_span_macro_inner!(core, core_span);
+_gen_span_macro_inner!(app, span);
+
 pub use escape_sequence;
@@ -8,7 +8,7 @@ use std::{
 };
 
 use bizarre_common::handle::Handle;
-use bizarre_logger::core_critical;
+use bizarre_logger::{core_critical, core_warn};
 
 use crate::material::{Material, MaterialInstance};
 
@@ -105,6 +105,25 @@ impl MaterialLoader {
         }
     }
 
+    /// Destroys the pipelines of the materials and forgets every material
+    /// and instance. The descriptor sets of the instances go with the
+    /// descriptor pool of the renderer. The device must be idle.
+    pub fn destroy(&mut self, device: &ash::Device) {
+        self.instances.clear();
+        self.instance_map.clear();
+        self.material_map.clear();
+
+        for (handle, mut material) in std::mem::take(&mut self.materials) {
+            match Arc::get_mut(&mut material) {
+                Some(material) => material.pipeline.destroy(device),
+                None => core_warn!(
+                    "Material {:?} is still in use, leaking its pipeline",
+                    handle
+                ),
+            }
+        }
+    }
+
     pub fn get_instance_handle(&self, name: &String) -> Option<&MaterialInstanceHandle> {
         self.instance_map.get(name)
     }
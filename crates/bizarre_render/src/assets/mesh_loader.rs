@@ -68,3 +68,21 @@ impl MeshLoader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bizarre_logger::{log_capture::capture_logs, LogLevel};
+
+    use super::{MeshHandle, MeshLoader};
+
+    #[test]
+    fn get_logs_missing_mesh() {
+        let loader = MeshLoader::default();
+
+        let logs = capture_logs(|| {
+            assert!(loader.get(MeshHandle::new(42)).is_none());
+        });
+
+        assert!(logs.contains(LogLevel::Error, "not found"));
+    }
+}
@@ -10,5 +10,5 @@ pub mod render_systems;
 pub mod vulkan_utils;
 
 pub use assets::*;
-pub use render::renderer::Renderer;
+pub use render::renderer::{Renderer, RendererSettings};
 pub use render::*;
@@ -6,7 +6,7 @@ use ash::{
     vk::{self, DeviceSize},
 };
 use bizarre_common::handle::Handle;
-use bizarre_logger::{core_debug, core_error};
+use bizarre_logger::{core_debug, core_error, core_span};
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 use thiserror::Error;
 
@@ -44,6 +44,18 @@ pub enum RenderException {
     RenderSkippedOutOfDate,
 }
 
+#[derive(Debug, Clone)]
+pub struct RendererSettings {
+    /// Lowered to the highest count the device supports
+    pub msaa_samples: u32,
+}
+
+impl Default for RendererSettings {
+    fn default() -> Self {
+        Self { msaa_samples: 2 }
+    }
+}
+
 pub struct Renderer {
     pub instance: VulkanInstance,
     pub device: VulkanDevice,
@@ -67,7 +79,7 @@ pub struct Renderer {
 }
 
 impl Renderer {
-    pub fn new(window: &winit::window::Window) -> Result<Self> {
+    pub fn new(window: &winit::window::Window, settings: &RendererSettings) -> Result<Self> {
         core_debug!("Constructing renderer!");
         let instance = VulkanInstance::new(window)?;
         let surface = unsafe {
@@ -81,7 +93,8 @@ impl Renderer {
         };
         let device = VulkanDevice::new(&instance, surface)?;
 
-        let max_msaa = vk::SampleCountFlags::TYPE_2;
+        let max_msaa = pick_sample_count(settings.msaa_samples, &instance, &device);
+        core_debug!("Renderer: using {:?} MSAA", max_msaa);
 
         let window_extent = vk::Extent2D {
             width: window.inner_size().width,
@@ -263,6 +276,8 @@ impl Renderer {
         render_scene: &mut RenderScene,
         material_loader: &MaterialLoader,
     ) -> Result<()> {
+        let _span = core_span!("Renderer::render", aggregate);
+
         let (present_index, mut suboptimal) = match self.acquire_image() {
             Ok((present_index, suboptimal)) => (present_index, suboptimal),
             Err(err) => match err.downcast_ref::<RenderException>() {
@@ -884,6 +899,25 @@ impl Renderer {
     }
 }
 
+fn pick_sample_count(
+    requested: u32,
+    instance: &VulkanInstance,
+    device: &VulkanDevice,
+) -> vk::SampleCountFlags {
+    let limits = unsafe {
+        instance
+            .get_physical_device_properties(device.physical_device)
+            .limits
+    };
+    let supported = limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+
+    [64, 32, 16, 8, 4, 2]
+        .into_iter()
+        .map(vk::SampleCountFlags::from_raw)
+        .find(|count| count.as_raw() <= requested && supported.contains(*count))
+        .unwrap_or(vk::SampleCountFlags::TYPE_1)
+}
+
 fn create_viewport(window_extent: vk::Extent2D) -> vk::Viewport {
     vk::Viewport {
         width: window_extent.width as f32,
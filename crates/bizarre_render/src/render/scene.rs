@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use anyhow::Result;
 use ash::vk;
-use bizarre_logger::core_debug;
+use bizarre_logger::{core_debug, core_span};
 
 use crate::{
     mesh::Mesh,
@@ -84,7 +84,25 @@ impl RenderScene {
         })
     }
 
+    /// The device must be idle
+    pub fn destroy(&mut self, device: &ash::Device) {
+        self.vbo.destroy(device);
+        self.ibo.destroy(device);
+
+        for buffer in self.transforms.iter_mut() {
+            buffer.destroy(device);
+        }
+        for buffer in self.directional_lights.iter_mut() {
+            buffer.destroy(device);
+        }
+
+        self.mesh_ranges.clear();
+        self.vbo_offset = 0;
+        self.ibo_offset = 0;
+    }
+
     pub fn upload_meshes(&mut self, meshes: &[*const Mesh], device: &VulkanDevice) -> Result<()> {
+        let _span = core_span!("RenderScene::upload_meshes");
         core_debug!("Uploading meshes to scene!");
         let (meshes, vbo_len, ibo_len) = meshes
             .iter()
@@ -3,10 +3,10 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Result};
 use ash::vk::ExtPrimitivesGeneratedQueryFn;
-use bizarre_logger::core_error;
-use specs::{Read, System, Write};
+use bizarre_common::system_error::TrySystem;
+use specs::{Read, Write};
 
 use crate::{
     material_loader::MaterialLoader, render_submitter::RenderSubmitter, scene::RenderScene,
@@ -19,7 +19,7 @@ impl RendererUpdateSystem {
     pub const DEFAULT_NAME: &'static str = "renderer_update_system";
 }
 
-impl<'a> System<'a> for RendererUpdateSystem {
+impl<'a> TrySystem<'a> for RendererUpdateSystem {
     type SystemData = (
         Write<'a, RenderSubmitter>,
         Write<'a, RendererResource>,
@@ -27,19 +27,16 @@ impl<'a> System<'a> for RendererUpdateSystem {
         Read<'a, MaterialLoader>,
     );
 
-    fn run(
+    fn try_run(
         &mut self,
         (mut submitter, renderer, mut render_scene, material_loader): Self::SystemData,
-    ) {
+    ) -> Result<()> {
         let render_package = submitter.finalize_submission();
-        let render_result = match renderer.lock() {
-            Ok(mut r) => r.render(&render_package, &mut render_scene, &material_loader),
-            Err(err) => Err(anyhow!("{}", err)),
-        };
+        let mut renderer = renderer.lock().map_err(|err| anyhow!("{}", err))?;
 
-        if let Err(err) = render_result {
-            core_error!("Failed to render the frame: {}", err);
-        }
+        renderer
+            .render(&render_package, &mut render_scene, &material_loader)
+            .map_err(|err| err.context("Failed to render the frame"))
     }
 }
 
@@ -157,4 +157,14 @@ impl<T> VulkanSliceBuffer<T> {
         unsafe { device.unmap_memory(self.memory) };
         drop(align)
     }
+
+    pub fn destroy(&mut self, device: &ash::Device) {
+        unsafe {
+            device.destroy_buffer(self.buffer, None);
+            self.buffer = vk::Buffer::null();
+
+            device.free_memory(self.memory, None);
+            self.memory = vk::DeviceMemory::null();
+        }
+    }
 }
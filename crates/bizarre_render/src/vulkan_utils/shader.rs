@@ -1,12 +1,13 @@
 use std::{
     fs::File,
     io::{Cursor, SeekFrom, Write},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::{LazyLock, RwLock},
 };
 
 use anyhow::{bail, Result};
 use ash::vk;
-use bizarre_logger::core_info;
+use bizarre_logger::{core_info, core_span};
 
 #[derive(Clone, Copy, Debug)]
 pub enum ShaderStage {
@@ -35,6 +36,21 @@ impl From<ShaderStage> for vk::ShaderStageFlags {
     }
 }
 
+pub const DEFAULT_SHADER_CACHE_DIR: &str = "cache/shaders/vulkan";
+
+static SHADER_CACHE_DIR: LazyLock<RwLock<PathBuf>> =
+    LazyLock::new(|| RwLock::new(PathBuf::from(DEFAULT_SHADER_CACHE_DIR)));
+
+/// Directory the compiled shaders are cached in, for the shaders loaded
+/// from now on
+pub fn set_shader_cache_dir<P: Into<PathBuf>>(path: P) {
+    *SHADER_CACHE_DIR.write().unwrap() = path.into();
+}
+
+pub fn shader_cache_dir() -> PathBuf {
+    SHADER_CACHE_DIR.read().unwrap().clone()
+}
+
 pub fn load_shader(path: &Path, shader_type: ShaderStage) -> Result<Vec<u32>> {
     if !path.is_file() {
         bail!(
@@ -44,7 +60,7 @@ pub fn load_shader(path: &Path, shader_type: ShaderStage) -> Result<Vec<u32>> {
     }
 
     let filename = path.file_name().unwrap().to_str().unwrap();
-    let cached_path = Path::new("cache/shaders/vulkan").join(format!("{filename}.spv"));
+    let cached_path = shader_cache_dir().join(format!("{filename}.spv"));
 
     let invalid_cache = if cached_path.is_file() {
         let source_metadata = std::fs::metadata(path)?;
@@ -57,6 +73,7 @@ pub fn load_shader(path: &Path, shader_type: ShaderStage) -> Result<Vec<u32>> {
 
     let spv = if invalid_cache {
         core_info!("Compiling shader '{}'", path.to_str().unwrap());
+        let _span = core_span!("load_shader: compile");
 
         let mut file = File::open(path)?;
         let artifact = compile_shader(&mut file, shader_type, filename)?;
@@ -1,5 +1,5 @@
 use bizarre_engine::{
-    core::App,
+    core::{error_policy::ErrorPolicy, App},
     layers::{camera_layer::CameraLayer, input_layer::InputLayer, visual_layer::VisualLayer},
 };
 use sandbox_layer::SandboxLayer;
@@ -9,10 +9,13 @@ mod sandbox_layer;
 fn main() {
     let mut app = App::builder()
         .name("Bizarre Engine")
+        .with_args(std::env::args().skip(1))
+        .with_stdin_console()
         .with_layer(InputLayer)
         .with_layer(VisualLayer)
         .with_layer(CameraLayer)
         .with_layer(SandboxLayer)
+        .with_system_error_policy("sandbox_setup", ErrorPolicy::StopApp)
         .build()
         .expect("Failed to build the App");
 
@@ -1,13 +1,15 @@
 use std::borrow::Borrow;
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use bizarre_engine::{
+    common::system_error::TrySystem,
     core::{
         app_builder::{self, AppBuilder},
-        layer::Layer,
-        schedule::ScheduleType,
+        layer::{Layer, LayerDependency},
+        schedule::{ScheduleType, SystemConfig},
         specs::{Builder, WorldExt},
     },
+    layers::visual_layer::VisualLayer,
     render::{
         material::builtin_materials::default_plain,
         material_loader::{self, MaterialLoader},
@@ -16,7 +18,7 @@ use bizarre_engine::{
         render_math::DirectionalLight,
         render_systems::RendererResource,
     },
-    specs::{Entities, Read, System, Write, WriteStorage},
+    specs::{Entities, Read, Write, WriteStorage},
 };
 use nalgebra_glm::vec3;
 
@@ -25,21 +27,24 @@ pub struct SandboxLayer;
 
 impl Layer for SandboxLayer {
     fn on_attach(&mut self, app_builder: &mut AppBuilder) -> Result<()> {
-        app_builder.add_system(
+        app_builder.add_try_system(
             ScheduleType::Setup,
             SandboxSetupSystem,
-            "sandbox_setup",
-            &[],
+            SystemConfig::new("sandbox_setup"),
         );
 
         Ok(())
     }
+
+    fn dependencies(&self) -> Vec<LayerDependency> {
+        vec![LayerDependency::of::<VisualLayer>()]
+    }
 }
 
 #[derive(Default)]
 pub struct SandboxSetupSystem;
 
-impl<'a> System<'a> for SandboxSetupSystem {
+impl<'a> TrySystem<'a> for SandboxSetupSystem {
     type SystemData = (
         Entities<'a>,
         Read<'a, RendererResource>,
@@ -50,7 +55,7 @@ impl<'a> System<'a> for SandboxSetupSystem {
         WriteStorage<'a, MaterialComponent>,
     );
 
-    fn run(
+    fn try_run(
         &mut self,
         (
             entities,
@@ -61,28 +66,26 @@ impl<'a> System<'a> for SandboxSetupSystem {
             mut light_storage,
             mut material_storage,
         ): Self::SystemData,
-    ) {
+    ) -> Result<()> {
         let monkey_mesh = get_mesh_loader_mut()
-            //TODO make a decent error handling for models
             .load_obj("assets/models/monkey.obj".into(), Some(&["monkey".into()]))
-            .unwrap()[0];
+            .context("Failed to load the monkey model")?[0];
 
         let cube_mesh = get_mesh_loader_mut()
             .load_obj("assets/models/cube.obj".into(), Some(&["cube".into()]))
-            .unwrap()[0];
+            .context("Failed to load the cube model")?[0];
 
         let default_material_instance = {
-            let renderer = renderer.lock().unwrap();
+            let renderer = renderer.lock().map_err(|err| anyhow!("{}", err))?;
 
             let default_material = default_plain(
                 renderer.max_msaa,
                 renderer.render_pass.handle,
                 &renderer.device,
-            )
-            .unwrap();
+            )?;
             let default_material = material_loader.add_material(default_material, "default".into());
             let default_material = material_loader.get_material(default_material);
-            let instance = renderer.create_material_instance(default_material).unwrap();
+            let instance = renderer.create_material_instance(default_material)?;
             material_loader.add_instance(instance, String::from("default_material_instance"))
         };
 
@@ -182,5 +185,7 @@ impl<'a> System<'a> for SandboxSetupSystem {
         //         &mut light_storage,
         //     )
         //     .build();
+
+        Ok(())
     }
 }